@@ -0,0 +1,192 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use qct_parser::dfa::Automaton;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr};
+
+/// Derive `TokenKind` and a `lexer()` constructor for a fieldless enum.
+///
+/// Variants take any number of `#[token("...")]` (literal, no text kept) or
+/// `#[regex("...")]` (text kept) rules, tried in declaration order. Exactly
+/// one variant is marked `#[unknown]`; variants marked `#[trivia]` are
+/// trivia. Rules matching the empty string are rejected, since the lexer can
+/// never emit them. Patterns are compiled once, by the macro.
+#[proc_macro_derive(Lex, attributes(token, regex, unknown, trivia))]
+pub fn derive_lex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "Lex can only be derived for enums",
+        ));
+    };
+
+    let name = &input.ident;
+    let mut unknown: Option<&Ident> = None;
+    let mut with_text = Vec::new();
+    let mut trivia = Vec::new();
+    let mut rules = Vec::new();
+
+    for variant in &data.variants {
+        let ident = &variant.ident;
+
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Lex variants cannot have fields",
+            ));
+        }
+
+        for attr in &variant.attrs {
+            if attr.path().is_ident("unknown") {
+                if unknown.replace(ident).is_some() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "Only one variant can be #[unknown]",
+                    ));
+                }
+            } else if attr.path().is_ident("trivia") {
+                trivia.push(ident);
+            } else if attr.path().is_ident("token") {
+                let literal: LitStr = attr.parse_args()?;
+                let bytes = literal.value().into_bytes();
+
+                if bytes.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        literal,
+                        "Lex tokens cannot be empty",
+                    ));
+                }
+
+                rules.push(quote! {
+                    (
+                        ::qct_parser::dfa::keyword_automaton([#(#bytes),*]),
+                        Self::#ident,
+                    )
+                });
+            } else if attr.path().is_ident("regex") {
+                let pattern: LitStr = attr.parse_args()?;
+                let automaton =
+                    qct_parser::regex::regex_automaton(&pattern.value())
+                        .map_err(|err| {
+                            syn::Error::new_spanned(&pattern, err)
+                        })?;
+
+                if automaton.validate_start().is_some() {
+                    return Err(syn::Error::new_spanned(
+                        pattern,
+                        "Lex patterns cannot match the empty string",
+                    ));
+                }
+
+                if !with_text.contains(&ident) {
+                    with_text.push(ident);
+                }
+
+                let tables = automaton_tokens(&automaton);
+                rules.push(quote! {
+                    (#tables, Self::#ident)
+                });
+            }
+        }
+    }
+
+    let Some(unknown) = unknown else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "Lex needs one variant marked #[unknown]",
+        ));
+    };
+
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::qct_parser::lexer::TokenKind
+            for #name #ty_generics #where_clause
+        {
+            fn unknown() -> Self {
+                Self::#unknown
+            }
+
+            fn has_text(&self) -> bool {
+                false #(|| *self == Self::#with_text)*
+            }
+
+            fn is_trivia(&self) -> bool {
+                false #(|| *self == Self::#trivia)*
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub fn lexer() -> ::qct_parser::lexer::Lexer<u8, Self> {
+                ::qct_parser::lexer::Lexer::new(
+                    ::core::iter::IntoIterator::into_iter([#(#rules),*])
+                        .collect(),
+                )
+            }
+        }
+    })
+}
+
+/// Rebuild a pattern's compiled automaton from its tables, so `lexer()`
+/// does not compile the pattern again at runtime
+fn automaton_tokens(automaton: &Automaton<u8>) -> TokenStream2 {
+    let accepting = (1..automaton.state_count())
+        .map(|state| automaton.is_accepting_state(state));
+    let transitions = automaton.edges().map(|(from, symbols, to)| {
+        let (start, end) = (symbols.start(), symbols.end());
+        quote! { builder.add_transition(#from, #to, #start..=#end); }
+    });
+
+    quote! {
+        {
+            let mut builder = ::qct_parser::dfa::AutomatonBuilder::<u8>::new();
+            #(builder.add_state(#accepting);)*
+            #(#transitions)*
+            builder.build()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn empty_rules_rejected() {
+        assert_eq!(
+            error(syn::parse_quote! {
+                enum Kind {
+                    #[token("")]
+                    Empty,
+                    #[unknown]
+                    Unknown,
+                }
+            }),
+            "Lex tokens cannot be empty",
+        );
+        assert_eq!(
+            error(syn::parse_quote! {
+                enum Kind {
+                    #[regex("a*|b")]
+                    Optional,
+                    #[unknown]
+                    Unknown,
+                }
+            }),
+            "Lex patterns cannot match the empty string",
+        );
+    }
+}
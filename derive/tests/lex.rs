@@ -0,0 +1,56 @@
+use qct_derive::Lex;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Lex)]
+enum CalcTokenKind {
+    #[token("let")]
+    Let,
+    #[regex("[a-z_][a-z0-9_]*")]
+    Ident,
+    #[regex(r"\d+(\.\d+)?")]
+    Number,
+    #[token("=")]
+    Assign,
+    #[token("+")]
+    #[token("-")]
+    Op,
+    #[regex(r"[ \t\n]+")]
+    #[trivia]
+    Whitespace,
+    #[unknown]
+    Unknown,
+}
+
+fn lex(source: &str) -> Vec<(CalcTokenKind, Option<String>)> {
+    let byte_iter = source.bytes();
+
+    CalcTokenKind::lexer()
+        .lex(byte_iter)
+        .map(|token| {
+            let text = token
+                .text()
+                .map(|text| String::from_utf8(text.to_vec()).unwrap());
+            (token.kind(), text)
+        })
+        .collect()
+}
+
+#[test]
+fn derived_lexer() {
+    use CalcTokenKind::*;
+
+    assert_eq!(
+        lex("let x1 = 2.5 + y - 3 ?"),
+        vec![
+            (Let, None),
+            (Ident, Some("x1".to_string())),
+            (Assign, None),
+            (Number, Some("2.5".to_string())),
+            (Op, None),
+            (Ident, Some("y".to_string())),
+            (Op, None),
+            (Number, Some("3".to_string())),
+            (Unknown, None),
+        ]
+    );
+    assert_eq!(lex("letter"), vec![(Ident, Some("letter".to_string()))]);
+}
@@ -0,0 +1,127 @@
+//! Lexing throughput for a small rule set and one with many keywords, plus
+//! plain-symbol lexing against the `Option`-wrapped flush input and range
+//! scanning against dense transition tables.
+//! Run with `cargo bench -p qct-parser`.
+
+use qct_parser::dense::DenseAutomaton;
+use qct_parser::dfa::{self, Automaton, AutomatonBuilder, START};
+use qct_parser::lexer::{Lexer, TokenKind};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BenchTokenKind {
+    Keyword,
+    Ident,
+    Number,
+    Whitespace,
+    Unknown,
+}
+
+impl TokenKind for BenchTokenKind {
+    fn unknown() -> Self {
+        Self::Unknown
+    }
+
+    fn has_text(&self) -> bool {
+        matches!(self, Self::Ident | Self::Number)
+    }
+}
+
+fn keyword(keyword: &str) -> (Automaton<u8>, BenchTokenKind) {
+    (
+        dfa::keyword_automaton(keyword.bytes()),
+        BenchTokenKind::Keyword,
+    )
+}
+
+fn rules() -> Vec<(Automaton<u8>, BenchTokenKind)> {
+    let mut ident = AutomatonBuilder::<u8>::new();
+    let rest = ident.add_state(true);
+    for range in [b'a'..=b'z', b'_'..=b'_'] {
+        ident.add_transition(START, rest, range.clone());
+        ident.add_transition(rest, rest, range);
+    }
+    ident.add_transition(rest, rest, b'0'..=b'9');
+
+    let mut number = AutomatonBuilder::<u8>::new();
+    let digits = number.add_state(true);
+    number.add_transition(START, digits, b'0'..=b'9');
+    number.add_transition(digits, digits, b'0'..=b'9');
+
+    let mut whitespace = AutomatonBuilder::<u8>::new();
+    let spaces = whitespace.add_state(true);
+    whitespace.add_transition(START, spaces, b' '..=b' ');
+    whitespace.add_transition(spaces, spaces, b' '..=b' ');
+
+    vec![
+        (ident.build(), BenchTokenKind::Ident),
+        (number.build(), BenchTokenKind::Number),
+        (whitespace.build(), BenchTokenKind::Whitespace),
+    ]
+}
+
+fn lexer() -> Lexer<u8, BenchTokenKind> {
+    let mut automata = vec![keyword("if"), keyword("while")];
+    automata.extend(rules());
+    Lexer::new(automata)
+}
+
+/// Fifty keywords `ka0` to `ke9`, in front of the usual rules
+fn keyword_rules() -> Vec<(Automaton<u8>, BenchTokenKind)> {
+    let mut automata: Vec<_> = (b'a'..=b'e')
+        .flat_map(|letter| {
+            (b'0'..=b'9').map(move |digit| [b'k', letter, digit])
+        })
+        .map(|word| keyword(std::str::from_utf8(&word).unwrap()))
+        .collect();
+    automata.extend(rules());
+    automata
+}
+
+fn time(mut run: impl FnMut() -> usize) -> Duration {
+    black_box(run());
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(run());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn report(name: &str, bytes: usize, elapsed: Duration) {
+    let throughput = bytes as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    println!("{name:>17}: {elapsed:>10.2?} ({throughput:.1} MiB/s)");
+}
+
+fn main() {
+    let source = "while x1 if 42 _y + ".repeat(50_000).into_bytes();
+    let lexer = lexer();
+
+    let plain = time(|| lexer.clone().lex(source.iter().copied()).count());
+    let flushes = time(|| {
+        let symbols = source.iter().copied().map(Some).chain(Some(None));
+        lexer.clone().lex_with_flushes(symbols).count()
+    });
+
+    report("lex", source.len(), plain);
+    report("lex_with_flushes", source.len(), flushes);
+
+    let source = "ka1 kb22 x kc3 kd kd9 12 ".repeat(40_000).into_bytes();
+    let lexer = Lexer::new(keyword_rules());
+    let keywords = time(|| lexer.clone().lex(source.iter().copied()).count());
+
+    report("50 keywords", source.len(), keywords);
+
+    let lexer = Lexer::new(
+        keyword_rules()
+            .iter()
+            .map(|(automaton, kind)| (DenseAutomaton::from(automaton), *kind))
+            .collect(),
+    );
+    let dense = time(|| lexer.clone().lex(source.iter().copied()).count());
+
+    report("50 keywords dense", source.len(), dense);
+}
@@ -0,0 +1,84 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qct_parser::dfa::{self, AutomatonBuilder, START};
+use qct_parser::lexer::{Lexer, Token, TokenKind};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum FuzzTokenKind {
+    If,
+    While,
+    Ident,
+    Float,
+    String,
+    LineComment,
+    BlockComment,
+    Unknown,
+}
+
+impl TokenKind for FuzzTokenKind {
+    fn unknown() -> Self {
+        Self::Unknown
+    }
+
+    fn has_text(&self) -> bool {
+        true
+    }
+}
+
+fn ident_dfa() -> dfa::Automaton<u8> {
+    let mut builder = AutomatonBuilder::new();
+    let rest = builder.add_state(true);
+    builder.add_transition(START, rest, b'a'..=b'z');
+    builder.add_transition(START, rest, b'_'..=b'_');
+    builder.add_transition(rest, rest, b'a'..=b'z');
+    builder.add_transition(rest, rest, b'_'..=b'_');
+    builder.add_transition(rest, rest, b'0'..=b'9');
+    builder.build()
+}
+
+fn lexer() -> Lexer<u8, FuzzTokenKind> {
+    Lexer::new(vec![
+        (dfa::keyword_automaton(*b"if"), FuzzTokenKind::If),
+        (dfa::keyword_automaton(*b"while"), FuzzTokenKind::While),
+        (ident_dfa(), FuzzTokenKind::Ident),
+        (dfa::float_automaton(), FuzzTokenKind::Float),
+        (
+            dfa::string_literal_automaton(b'"', b'\\'),
+            FuzzTokenKind::String,
+        ),
+        (
+            dfa::line_comment_automaton(b"//"),
+            FuzzTokenKind::LineComment,
+        ),
+        (
+            dfa::block_comment_automaton(b"/*", b"*/"),
+            FuzzTokenKind::BlockComment,
+        ),
+    ])
+}
+
+fn texts(tokens: impl Iterator<Item = Token<u8, FuzzTokenKind>>) -> Vec<u8> {
+    tokens
+        .flat_map(|token| token.text().unwrap().to_vec())
+        .collect()
+}
+
+fuzz_target!(|data: &[u8]| {
+    // Every symbol ends up in some token
+    assert_eq!(texts(lexer().lex(data.iter().copied())), data);
+
+    // Spans tile the input in order
+    let mut end = 0;
+    for token in lexer().lex(data.iter().copied()) {
+        assert_eq!(token.span().start, end);
+        end = token.span().end;
+    }
+    assert_eq!(end, data.len());
+
+    // Without a flush, the pending token is held back but nothing is
+    // reordered
+    let unflushed =
+        texts(lexer().lex_with_flushes(data.iter().copied().map(Some)));
+    assert!(data.starts_with(&unflushed));
+});
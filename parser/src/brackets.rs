@@ -0,0 +1,152 @@
+use crate::lexer::{Token, TokenKind};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Bracket pairing failure; indices refer to positions in the token slice
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BracketError {
+    Unopened(usize),
+    Unclosed(usize),
+    Mismatched { open: usize, close: usize },
+    TooDeep(usize),
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Unopened(idx) => {
+                write!(f, "Closing bracket at token {idx} was never opened")
+            }
+            Self::Unclosed(idx) => {
+                write!(f, "Opening bracket at token {idx} was never closed")
+            }
+            Self::Mismatched { open, close } => write!(
+                f,
+                "Closing bracket at token {close} does not match opening \
+                bracket at token {open}"
+            ),
+            Self::TooDeep(idx) => {
+                write!(f, "Bracket at token {idx} exceeds the nesting limit")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BracketError {}
+
+/// Match opening and closing bracket tokens, given as `(open, close)` kind
+/// pairs, returning the token indices of each matched pair in closing order.
+/// Nesting deeper than `max_depth`, if given, is an error.
+pub fn pair_brackets<Sym: Copy + Ord, K: TokenKind>(
+    tokens: &[Token<Sym, K>],
+    pairs: &[(K, K)],
+    max_depth: Option<usize>,
+) -> Result<Vec<(usize, usize)>, BracketError> {
+    let mut stack: Vec<(usize, K)> = Vec::new();
+    let mut matched = Vec::new();
+
+    for (idx, token) in tokens.iter().enumerate() {
+        let kind = token.kind();
+
+        if let Some((_, close)) = pairs.iter().find(|(open, _)| *open == kind) {
+            if max_depth.is_some_and(|max| stack.len() >= max) {
+                return Err(BracketError::TooDeep(idx));
+            }
+
+            stack.push((idx, *close));
+        } else if pairs.iter().any(|(_, close)| *close == kind) {
+            match stack.pop() {
+                Some((open, close)) if close == kind => {
+                    matched.push((open, idx))
+                }
+                Some((open, _)) => {
+                    return Err(BracketError::Mismatched { open, close: idx })
+                }
+                None => return Err(BracketError::Unopened(idx)),
+            }
+        }
+    }
+
+    match stack.pop() {
+        Some((open, _)) => Err(BracketError::Unclosed(open)),
+        None => Ok(matched),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa;
+    use crate::lexer::Lexer;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum BracketTokenKind {
+        OpenParen,
+        CloseParen,
+        OpenBrace,
+        CloseBrace,
+        Unknown,
+    }
+
+    impl TokenKind for BracketTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            false
+        }
+    }
+
+    const PAIRS: [(BracketTokenKind, BracketTokenKind); 2] = [
+        (BracketTokenKind::OpenParen, BracketTokenKind::CloseParen),
+        (BracketTokenKind::OpenBrace, BracketTokenKind::CloseBrace),
+    ];
+
+    fn lex(source: &str) -> Vec<Token<u8, BracketTokenKind>> {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"("), BracketTokenKind::OpenParen),
+            (dfa::keyword_automaton(*b")"), BracketTokenKind::CloseParen),
+            (dfa::keyword_automaton(*b"{"), BracketTokenKind::OpenBrace),
+            (dfa::keyword_automaton(*b"}"), BracketTokenKind::CloseBrace),
+        ]);
+
+        lexer.lex(source.bytes()).collect()
+    }
+
+    #[test]
+    fn pairs_nested_brackets() {
+        assert_eq!(
+            pair_brackets(&lex("({})()"), &PAIRS, None),
+            Ok(vec![(1, 2), (0, 3), (4, 5)])
+        );
+    }
+
+    #[test]
+    fn max_depth_exceeded() {
+        let tokens = lex("((( )))");
+
+        assert_eq!(
+            pair_brackets(&tokens, &PAIRS, Some(2)),
+            Err(BracketError::TooDeep(2))
+        );
+        assert!(pair_brackets(&tokens, &PAIRS, Some(3)).is_ok());
+    }
+
+    #[test]
+    fn unbalanced_brackets() {
+        assert_eq!(
+            pair_brackets(&lex("(}"), &PAIRS, None),
+            Err(BracketError::Mismatched { open: 0, close: 1 })
+        );
+        assert_eq!(
+            pair_brackets(&lex(")"), &PAIRS, None),
+            Err(BracketError::Unopened(0))
+        );
+        assert_eq!(
+            pair_brackets(&lex("{()"), &PAIRS, None),
+            Err(BracketError::Unclosed(0))
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::dfa::{Automaton, AutomatonBuilder, START};
+use crate::recognizer::Recognizer;
+use alloc::vec::Vec;
+
+const DEAD: u32 = u32::MAX;
+
+/// Byte automaton with a flat 256-entry transition table per state, so each
+/// transition is a single index. Tables take 1 KiB per state, trading memory
+/// for speed over the range scan in `Automaton`.
+#[derive(Clone)]
+pub struct DenseAutomaton {
+    table: Vec<[u32; 256]>,
+    accepting: Vec<bool>,
+    current_state: Option<usize>,
+    previous_accepting: bool,
+    max_length: Option<usize>,
+    length: usize,
+}
+
+impl From<&Automaton<u8>> for DenseAutomaton {
+    fn from(automaton: &Automaton<u8>) -> Self {
+        let table = automaton
+            .states
+            .iter()
+            .map(|state| {
+                let mut row = [DEAD; 256];
+
+                for (range, to) in state.edges() {
+                    row[*range.start() as usize..=*range.end() as usize]
+                        .fill(to as u32);
+                }
+
+                row
+            })
+            .collect();
+
+        Self {
+            table,
+            accepting: automaton.states.iter().map(|s| s.accepting).collect(),
+            current_state: Some(START),
+            previous_accepting: false,
+            max_length: automaton.max_length,
+            length: 0,
+        }
+    }
+}
+
+impl AutomatonBuilder<u8> {
+    /// Build straight into a `DenseAutomaton`, for lexers where transition
+    /// speed matters more than the 1 KiB per state
+    pub fn build_dense(self) -> DenseAutomaton {
+        DenseAutomaton::from(&self.build())
+    }
+}
+
+impl Recognizer<u8> for DenseAutomaton {
+    fn transition(&mut self, symbol: Option<u8>) {
+        self.previous_accepting = self.is_accepting();
+
+        if let Some(state) = self.current_state {
+            if self.max_length.is_some_and(|max| self.length >= max) {
+                self.current_state = None;
+            } else {
+                self.current_state = symbol
+                    .map(|symbol| self.table[state][symbol as usize])
+                    .filter(|next| *next != DEAD)
+                    .map(|next| next as usize);
+                self.length += 1;
+            }
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.current_state.is_some()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.current_state.is_some_and(|idx| self.accepting[idx])
+    }
+
+    fn is_previous_accepting(&self) -> bool {
+        self.previous_accepting
+    }
+
+    fn reset(&mut self) {
+        self.current_state = Some(START);
+        self.previous_accepting = false;
+        self.length = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_matches_ranges() {
+        let build = || {
+            let mut builder = AutomatonBuilder::<u8>::new();
+            let digits = builder.add_state(true);
+            let fraction = builder.add_state(true);
+            builder.add_transition(START, digits, b'0'..=b'9');
+            builder.add_transition(digits, digits, b'0'..=b'9');
+            builder.add_transition(digits, fraction, b'.'..=b'.');
+            builder.add_transition(fraction, fraction, b'0'..=b'9');
+            builder
+        };
+        let mut ranges = build().build();
+        let mut dense = build().build_dense();
+
+        for input in ["", "1", "12.5", "1.2.", ".5", "9x"] {
+            ranges.reset();
+            dense.reset();
+
+            for byte in input.bytes() {
+                ranges.transition(Some(byte));
+                dense.transition(Some(byte));
+
+                assert_eq!(dense.is_alive(), ranges.is_alive());
+                assert_eq!(dense.is_accepting(), ranges.is_accepting());
+            }
+        }
+    }
+}
@@ -1,14 +1,174 @@
-use std::ops::RangeInclusive;
+use crate::interval::IntervalSet;
+use crate::nfa::{DeterminizationError, Nfa};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::RangeInclusive;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 pub const START: usize = 0;
 
+/// Symbols with a successor and predecessor, needed by constructions that
+/// split transition ranges (e.g. determinization)
+pub trait Symbol: Copy + Ord {
+    fn successor(self) -> Option<Self>;
+    fn predecessor(self) -> Option<Self>;
+}
+
+impl Symbol for u8 {
+    fn successor(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    fn predecessor(self) -> Option<Self> {
+        self.checked_sub(1)
+    }
+}
+
+impl Symbol for char {
+    fn successor(self) -> Option<Self> {
+        match self {
+            '\u{D7FF}' => Some('\u{E000}'),
+            _ => char::from_u32(u32::from(self) + 1),
+        }
+    }
+
+    fn predecessor(self) -> Option<Self> {
+        match self {
+            '\u{E000}' => Some('\u{D7FF}'),
+            _ => u32::from(self).checked_sub(1).and_then(char::from_u32),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Automaton<Sym: Copy + Ord> {
-    states: Vec<State<Sym>>,
+    pub(crate) states: Vec<State<Sym>>,
     current_state: Option<usize>,
     previous_accepting: bool,
+    pub(crate) max_length: Option<usize>,
+    length: usize,
 }
 
 impl<Sym: Copy + Ord> Automaton<Sym> {
+    pub(crate) fn from_states(mut states: Vec<State<Sym>>) -> Self {
+        states.iter_mut().for_each(State::index);
+
+        Self {
+            states,
+            current_state: Some(START),
+            previous_accepting: false,
+            max_length: None,
+            length: 0,
+        }
+    }
+
+    /// Treat the automaton as dead once it has consumed `max_length` symbols
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    pub fn accepts(&self, input: impl IntoIterator<Item = Sym>) -> bool {
+        self.run(input)
+            .map(|idx| self.states[idx].accepting)
+            .unwrap_or(false)
+    }
+
+    /// Whether running `input` from START leaves the automaton alive
+    pub fn is_prefix(&self, input: impl IntoIterator<Item = Sym>) -> bool {
+        self.run(input).is_some()
+    }
+
+    /// State reached from START after `input`, without moving the cursor
+    fn run(&self, input: impl IntoIterator<Item = Sym>) -> Option<usize> {
+        let mut state = Some(START);
+
+        for (length, symbol) in input.into_iter().enumerate() {
+            if self.max_length.is_some_and(|max| length >= max) {
+                return None;
+            }
+
+            state =
+                state.and_then(|idx| self.states[idx].transition(Some(symbol)));
+        }
+
+        state
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_accepting_state(&self, state: usize) -> bool {
+        self.states[state].accepting
+    }
+
+    /// Every transition range as `(from, symbols, to)`, ordered by state
+    pub fn edges(
+        &self,
+    ) -> impl Iterator<Item = (usize, &RangeInclusive<Sym>, usize)> {
+        self.states.iter().enumerate().flat_map(|(from, state)| {
+            state.edges().map(move |(symbols, to)| (from, symbols, to))
+        })
+    }
+
+    /// Same check as `AutomatonBuilder::validate_start`, for automata built
+    /// some other way
+    pub fn validate_start(&self) -> Option<ValidationIssue> {
+        self.states[START]
+            .accepting
+            .then_some(ValidationIssue::AcceptingStart)
+    }
+
+    /// Number of stored transition ranges across all states
+    pub fn transition_count(&self) -> usize {
+        self.states
+            .iter()
+            .flat_map(|state| state.transitions.iter())
+            .map(|(set, _)| set.ranges().len())
+            .sum()
+    }
+
+    /// Approximate heap and inline size of the automaton's tables in bytes
+    pub fn memory_size(&self) -> usize {
+        let range_size = core::mem::size_of::<RangeInclusive<Sym>>();
+        let entry_size = core::mem::size_of::<(IntervalSet<Sym>, usize)>();
+        let usize_size = core::mem::size_of::<usize>();
+
+        core::mem::size_of::<Self>()
+            + self.states.len() * core::mem::size_of::<State<Sym>>()
+            + self
+                .states
+                .iter()
+                .map(|state| state.transitions.len() * entry_size)
+                .sum::<usize>()
+            + self.transition_count() * (2 * range_size + usize_size)
+    }
+
+    /// Whether no string is accepted, i.e. no accepting state is reachable
+    pub fn is_empty(&self) -> bool {
+        let mut visited = vec![false; self.states.len()];
+        let mut stack = vec![START];
+
+        while let Some(idx) = stack.pop() {
+            if visited[idx] {
+                continue;
+            }
+
+            if self.states[idx].accepting {
+                return false;
+            }
+
+            visited[idx] = true;
+            stack.extend(self.states[idx].edges().map(|(_, to)| to));
+        }
+
+        true
+    }
+
     pub fn transition(&mut self, symbol: Option<Sym>) {
         self.previous_accepting = self
             .current_state
@@ -16,7 +176,12 @@ impl<Sym: Copy + Ord> Automaton<Sym> {
             .unwrap_or(false);
 
         if let Some(state_idx) = self.current_state {
-            self.current_state = self.states[state_idx].transition(symbol)
+            if self.max_length.is_some_and(|max| self.length >= max) {
+                self.current_state = None;
+            } else {
+                self.current_state = self.states[state_idx].transition(symbol);
+                self.length += 1;
+            }
         }
     }
 
@@ -24,6 +189,12 @@ impl<Sym: Copy + Ord> Automaton<Sym> {
         self.previous_accepting
     }
 
+    /// Whether the symbols consumed since the last reset are accepted
+    pub fn is_accepting(&self) -> bool {
+        self.current_state
+            .is_some_and(|idx| self.states[idx].accepting)
+    }
+
     pub fn is_alive(&self) -> bool {
         self.current_state.is_some()
     }
@@ -31,41 +202,309 @@ impl<Sym: Copy + Ord> Automaton<Sym> {
     pub fn reset(&mut self) {
         self.current_state = Some(START);
         self.previous_accepting = false;
+        self.length = 0;
+    }
+
+    /// Remove states unreachable from START, renumbering the rest in
+    /// breadth-first order. The current state is kept, as it is reachable.
+    pub fn trim(&mut self) {
+        let mut numbering = vec![None; self.states.len()];
+        let mut order = vec![START];
+        numbering[START] = Some(0);
+        let mut next = 0;
+
+        while let Some(&idx) = order.get(next) {
+            next += 1;
+
+            for (_, to) in &self.states[idx].transitions {
+                if numbering[*to].is_none() {
+                    numbering[*to] = Some(order.len());
+                    order.push(*to);
+                }
+            }
+        }
+
+        let mut states: Vec<_> = self.states.drain(..).map(Some).collect();
+        self.states = order
+            .iter()
+            .map(|idx| {
+                let mut state = states[*idx].take().unwrap();
+
+                for (_, to) in &mut state.transitions {
+                    *to = numbering[*to].unwrap();
+                }
+
+                state.index();
+                state
+            })
+            .collect();
+        self.current_state = self.current_state.and_then(|idx| numbering[idx]);
+    }
+}
+
+/// Serialized as its state table and length limit; run state is not kept
+#[cfg(feature = "serde")]
+impl<Sym: Copy + Ord + Serialize> Serialize for Automaton<Sym> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        (&self.states, self.max_length).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Sym: Copy + Ord + Deserialize<'de>> Deserialize<'de>
+    for Automaton<Sym>
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let (states, max_length): (Vec<State<Sym>>, Option<usize>) =
+            Deserialize::deserialize(deserializer)?;
+
+        if states.is_empty() {
+            return Err(de::Error::custom("Automaton has no START state"));
+        }
+
+        if states
+            .iter()
+            .any(|state| state.edges().any(|(_, to)| to >= states.len()))
+        {
+            return Err(de::Error::custom(
+                "Transition target exceeds state count",
+            ));
+        }
+
+        let mut automaton = Self::from_states(states);
+        automaton.max_length = max_length;
+        Ok(automaton)
+    }
+}
+
+impl<Sym: Symbol> Automaton<Sym> {
+    /// Merge adjacent transition ranges sharing a target state
+    pub fn compact(&mut self) {
+        for state in &mut self.states {
+            for (set, _) in &mut state.transitions {
+                set.merge_adjacent();
+            }
+
+            state.index();
+        }
+    }
+}
+
+impl Automaton<char> {
+    /// Build an automaton from a glob pattern supporting `*`, `?` and
+    /// bracketed classes such as `[abc]`, `[a-z]` or `[]a]`
+    pub fn from_glob(pattern: &str) -> Self {
+        let mut nfa = Nfa::new();
+        let mut current = START;
+        let mut chars = pattern.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let next = nfa.add_state(false);
+
+            match c {
+                '*' => {
+                    nfa.add_epsilon(current, next);
+                    nfa.add_transition(next, next, '\0'..=char::MAX);
+                }
+                '?' => nfa.add_transition(current, next, '\0'..=char::MAX),
+                '[' if pattern_class_end(chars.clone()).is_some() => {
+                    // A leading ']' is a member rather than the end
+                    let mut class = Vec::from_iter(chars.next());
+
+                    for c in chars.by_ref().take_while(|c| *c != ']') {
+                        class.push(c);
+                    }
+
+                    let mut members = class.iter().peekable();
+
+                    while let Some(first) = members.next() {
+                        let mut last = *first;
+
+                        if members.peek() == Some(&&'-') {
+                            let mut lookahead = members.clone();
+                            lookahead.next();
+
+                            if let Some(end) = lookahead.next() {
+                                last = *end;
+                                members = lookahead;
+                            }
+                        }
+
+                        nfa.add_transition(current, next, *first..=last);
+                    }
+                }
+                _ => nfa.add_transition(current, next, c..=c),
+            }
+
+            current = next;
+        }
+
+        nfa.set_accepting(current, true);
+        nfa.to_dfa()
     }
 }
 
-struct State<Sym: Copy + Ord> {
-    transitions: Vec<(RangeInclusive<Sym>, usize)>,
-    accepting: bool,
+fn pattern_class_end(chars: impl Iterator<Item = char>) -> Option<usize> {
+    chars
+        .enumerate()
+        .skip(1)
+        .find(|(_, c)| *c == ']')
+        .map(|(i, _)| i)
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct State<Sym: Copy + Ord> {
+    /// Symbols leading to each target state, one entry per target
+    pub(crate) transitions: Vec<(IntervalSet<Sym>, usize)>,
+    pub(crate) accepting: bool,
+    /// Every range of `transitions` with its target, sorted by start.
+    /// Built by `index` once the transitions are final.
+    #[cfg_attr(feature = "serde", serde(skip, default = "Vec::new"))]
+    lookup: Vec<(RangeInclusive<Sym>, usize)>,
+    /// Ranges to different targets share symbols, so `lookup` cannot be
+    /// searched and the first matching target wins
+    #[cfg_attr(feature = "serde", serde(skip))]
+    overlapping: bool,
 }
 
 impl<Sym: Copy + Ord> State<Sym> {
-    fn transition(&self, symbol: Option<Sym>) -> Option<usize> {
-        if let Some(symbol) = symbol {
-            for t in &self.transitions {
-                let (range, next_state) = t;
+    pub(crate) fn transition(&self, symbol: Option<Sym>) -> Option<usize> {
+        let symbol = symbol?;
+
+        if self.overlapping {
+            return self
+                .transitions
+                .iter()
+                .find(|(set, _)| set.contains(symbol))
+                .map(|(_, to)| *to);
+        }
 
-                if range.contains(&symbol) {
-                    return Some(*next_state);
+        let idx = self
+            .lookup
+            .binary_search_by(|(range, _)| {
+                if *range.end() < symbol {
+                    Ordering::Less
+                } else if *range.start() > symbol {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
                 }
+            })
+            .ok()?;
+
+        Some(self.lookup[idx].1)
+    }
+
+    /// Rebuild the sorted range lookup used by `transition`
+    pub(crate) fn index(&mut self) {
+        let mut lookup: Vec<_> = self
+            .edges()
+            .map(|(range, to)| (range.clone(), to))
+            .collect();
+        lookup.sort_unstable_by(|(a, _), (b, _)| a.start().cmp(b.start()));
+        self.lookup = lookup;
+        self.overlapping = self.overlap().is_some();
+    }
+
+    /// Two ranges of the lookup sharing a symbol
+    fn overlap(&self) -> Option<(RangeInclusive<Sym>, RangeInclusive<Sym>)> {
+        self.lookup
+            .windows(2)
+            .find(|pair| pair[0].0.end() >= pair[1].0.start())
+            .map(|pair| (pair[0].0.clone(), pair[1].0.clone()))
+    }
+
+    pub(crate) fn add_transition(
+        &mut self,
+        symbols: RangeInclusive<Sym>,
+        to: usize,
+    ) {
+        match self
+            .transitions
+            .iter_mut()
+            .find(|(_, target)| *target == to)
+        {
+            Some((set, _)) => set.insert(symbols),
+            None => {
+                let mut set = IntervalSet::new();
+                set.insert(symbols);
+                self.transitions.push((set, to));
             }
         }
+    }
 
-        None
+    pub(crate) fn edges(
+        &self,
+    ) -> impl Iterator<Item = (&RangeInclusive<Sym>, usize)> + Clone {
+        self.transitions
+            .iter()
+            .flat_map(|(set, to)| set.ranges().iter().map(|range| (range, *to)))
     }
 
-    fn new(accepting: bool) -> Self {
+    pub(crate) fn new(accepting: bool) -> Self {
         Self {
             transitions: Vec::new(),
             accepting,
+            lookup: Vec::new(),
+            overlapping: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ValidationIssue {
+    /// A non-accepting state with no outgoing transitions
+    DeadEnd(usize),
+    /// START is accepting, so the automaton matches the empty string
+    AcceptingStart,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BuildError<Sym> {
+    /// Two transitions from `state` to different states share symbols, so
+    /// the automaton would not be deterministic
+    OverlappingRanges {
+        state: usize,
+        first: RangeInclusive<Sym>,
+        second: RangeInclusive<Sym>,
+    },
+}
+
+impl<Sym: fmt::Debug> fmt::Display for BuildError<Sym> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OverlappingRanges {
+                state,
+                first,
+                second,
+            } => write!(
+                f,
+                "Transitions from state {state} over {first:?} and \
+                 {second:?} overlap"
+            ),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl<Sym: fmt::Debug> std::error::Error for BuildError<Sym> {}
+
 pub struct AutomatonBuilder<Sym: Copy + Ord> {
     states: Vec<State<Sym>>,
 }
 
+impl<Sym: Copy + Ord> Default for AutomatonBuilder<Sym> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Sym: Copy + Ord> AutomatonBuilder<Sym> {
     pub fn new() -> Self {
         Self {
@@ -79,6 +518,14 @@ impl<Sym: Copy + Ord> AutomatonBuilder<Sym> {
         idx
     }
 
+    pub fn set_accepting(&mut self, state: usize, accepting: bool) {
+        if state >= self.states.len() {
+            panic!("State argument exceeds state count");
+        }
+
+        self.states[state].accepting = accepting;
+    }
+
     pub fn add_transition(
         &mut self,
         from: usize,
@@ -93,18 +540,87 @@ impl<Sym: Copy + Ord> AutomatonBuilder<Sym> {
             panic!("Transition 'to' argument exceeds state count");
         }
 
-        self.states[from].transitions.push((symbols, to));
+        self.states[from].add_transition(symbols, to);
+    }
+
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        self.states
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| {
+                !state.accepting && state.transitions.is_empty()
+            })
+            .map(|(idx, _)| ValidationIssue::DeadEnd(idx))
+            .collect()
     }
 
+    /// Check START separately from `validate`: an accepting START makes the
+    /// automaton match the empty string, which a lexer cannot emit
+    pub fn validate_start(&self) -> Option<ValidationIssue> {
+        self.states[START]
+            .accepting
+            .then_some(ValidationIssue::AcceptingStart)
+    }
+
+    /// Overlapping transitions to different states resolve to the target
+    /// added first; `try_build` rejects them instead
     pub fn build(self) -> Automaton<Sym> {
-        Automaton {
-            states: self.states,
-            current_state: Some(START),
-            previous_accepting: false,
+        Automaton::from_states(self.states)
+    }
+}
+
+impl<Sym: Symbol> AutomatonBuilder<Sym> {
+    /// Like `build`, but rejects overlapping transitions to different
+    /// states and merges adjacent ranges to the same state
+    pub fn try_build(self) -> Result<Automaton<Sym>, BuildError<Sym>> {
+        let mut automaton = Automaton::from_states(self.states);
+
+        for (state, state_tables) in automaton.states.iter().enumerate() {
+            if let Some((first, second)) = state_tables.overlap() {
+                return Err(BuildError::OverlappingRanges {
+                    state,
+                    first,
+                    second,
+                });
+            }
+        }
+
+        automaton.compact();
+        Ok(automaton)
+    }
+
+    /// Add transitions over every symbol of `alphabet` outside `excluded`
+    pub fn add_transition_except(
+        &mut self,
+        from: usize,
+        to: usize,
+        alphabet: RangeInclusive<Sym>,
+        excluded: impl IntoIterator<Item = RangeInclusive<Sym>>,
+    ) {
+        let mut excluded_set = IntervalSet::new();
+
+        for range in excluded {
+            excluded_set.insert(range);
+        }
+
+        for range in excluded_set.complement(alphabet).ranges() {
+            self.add_transition(from, to, range.clone());
         }
     }
 }
 
+impl AutomatonBuilder<char> {
+    /// Add transitions for a negated class `[^...]` over all of Unicode
+    pub fn add_negated_class(
+        &mut self,
+        from: usize,
+        to: usize,
+        excluded: impl IntoIterator<Item = RangeInclusive<char>>,
+    ) {
+        self.add_transition_except(from, to, '\0'..=char::MAX, excluded);
+    }
+}
+
 pub fn keyword_automaton<Sym: Copy + Ord>(
     keyword: impl IntoIterator<Item = Sym>,
 ) -> Automaton<Sym> {
@@ -124,6 +640,233 @@ pub fn keyword_automaton<Sym: Copy + Ord>(
     builder.build()
 }
 
+/// Build an automaton matching `open`, then anything up to and including the
+/// first occurrence of `close`
+pub fn block_comment_automaton(open: &[u8], close: &[u8]) -> Automaton<u8> {
+    if close.is_empty() {
+        panic!("Block comment 'close' argument must be nonempty");
+    }
+
+    let mut builder = AutomatonBuilder::new();
+    let mut current = START;
+
+    for byte in open {
+        let next = builder.add_state(false);
+        builder.add_transition(current, next, *byte..=*byte);
+        current = next;
+    }
+
+    // State `body + k` means the last k symbols matched a prefix of `close`
+    let body = current;
+    for k in 1..=close.len() {
+        builder.add_state(k == close.len());
+    }
+
+    for k in 0..close.len() {
+        let mut targets = [0; 256];
+
+        for (byte, target) in targets.iter_mut().enumerate() {
+            let mut seen = close[..k].to_vec();
+            seen.push(byte as u8);
+            *target = (0..=seen.len())
+                .rev()
+                .find(|len| close.starts_with(&seen[seen.len() - len..]))
+                .unwrap_or(0);
+        }
+
+        add_byte_transitions(&mut builder, body + k, |byte| {
+            Some(body + targets[usize::from(byte)])
+        });
+    }
+
+    builder.build()
+}
+
+/// Build an automaton matching `prefix` followed by anything up to, but not
+/// including, the next newline
+pub fn line_comment_automaton(prefix: &[u8]) -> Automaton<u8> {
+    if prefix.is_empty() {
+        panic!("Line comment 'prefix' argument must be nonempty");
+    }
+
+    let mut builder = AutomatonBuilder::new();
+    let mut current = START;
+
+    for (idx, byte) in prefix.iter().enumerate() {
+        let next = builder.add_state(idx == prefix.len() - 1);
+        builder.add_transition(current, next, *byte..=*byte);
+        current = next;
+    }
+
+    add_byte_transitions(&mut builder, current, |byte| {
+        (byte != b'\n').then_some(current)
+    });
+
+    builder.build()
+}
+
+/// Build an automaton matching a `quote`-delimited string in which `escape`
+/// makes the following symbol (including `quote`) literal
+pub fn string_literal_automaton(quote: u8, escape: u8) -> Automaton<u8> {
+    if quote == escape {
+        panic!("String literal 'quote' and 'escape' arguments must differ");
+    }
+
+    let mut builder = AutomatonBuilder::new();
+    let body = builder.add_state(false);
+    let escaped = builder.add_state(false);
+    let end = builder.add_state(true);
+
+    builder.add_transition(START, body, quote..=quote);
+    add_byte_transitions(&mut builder, body, |byte| {
+        Some(match byte {
+            _ if byte == quote => end,
+            _ if byte == escape => escaped,
+            _ => body,
+        })
+    });
+    builder.add_transition(escaped, body, 0..=u8::MAX);
+
+    builder.build()
+}
+
+/// Build an automaton matching `[+-]?digits(.digits)?([eE][+-]?digits)?`.
+/// The integer part may also be omitted when a fraction is present, as in
+/// `.5` or `+.5`, but a trailing `.` with no fraction digits is rejected.
+pub fn float_automaton() -> Automaton<u8> {
+    let digits = b'0'..=b'9';
+
+    let mut builder = AutomatonBuilder::new();
+    let sign = builder.add_state(false);
+    let integer = builder.add_state(true);
+    let point = builder.add_state(false);
+    let fraction = builder.add_state(true);
+    let exponent = builder.add_state(false);
+    let exponent_sign = builder.add_state(false);
+    let exponent_digits = builder.add_state(true);
+
+    builder.add_transition(START, sign, b'+'..=b'+');
+    builder.add_transition(START, sign, b'-'..=b'-');
+
+    for from in [START, sign] {
+        builder.add_transition(from, integer, digits.clone());
+        builder.add_transition(from, point, b'.'..=b'.');
+    }
+
+    builder.add_transition(integer, integer, digits.clone());
+    builder.add_transition(integer, point, b'.'..=b'.');
+    builder.add_transition(point, fraction, digits.clone());
+    builder.add_transition(fraction, fraction, digits.clone());
+
+    for from in [integer, fraction] {
+        builder.add_transition(from, exponent, b'e'..=b'e');
+        builder.add_transition(from, exponent, b'E'..=b'E');
+    }
+
+    builder.add_transition(exponent, exponent_sign, b'+'..=b'+');
+    builder.add_transition(exponent, exponent_sign, b'-'..=b'-');
+    builder.add_transition(exponent, exponent_digits, digits.clone());
+    builder.add_transition(exponent_sign, exponent_digits, digits.clone());
+    builder.add_transition(exponent_digits, exponent_digits, digits);
+
+    builder.build()
+}
+
+/// Add transitions from `from` for every byte, merging runs of bytes with
+/// the same target into a single range
+fn add_byte_transitions(
+    builder: &mut AutomatonBuilder<u8>,
+    from: usize,
+    target: impl Fn(u8) -> Option<usize>,
+) {
+    let mut run_start = 0u8;
+
+    for byte in 0..=u8::MAX {
+        let run_ends = byte == u8::MAX || target(byte) != target(byte + 1);
+
+        if run_ends {
+            if let Some(to) = target(byte) {
+                builder.add_transition(from, to, run_start..=byte);
+            }
+
+            run_start = byte.wrapping_add(1);
+        }
+    }
+}
+
+pub fn concat<Sym: Symbol>(
+    first: &Automaton<Sym>,
+    second: &Automaton<Sym>,
+) -> Automaton<Sym> {
+    let mut nfa = Nfa::from_dfa(first);
+    let offset = nfa.append_dfa(second);
+
+    for idx in 0..offset {
+        if nfa.is_accepting(idx) {
+            nfa.set_accepting(idx, false);
+            nfa.add_epsilon(idx, offset + START);
+        }
+    }
+
+    nfa.to_dfa()
+}
+
+/// Words made of exactly `n` words of `automaton`; for `n == 0` that is
+/// only the empty string
+pub fn repeat_exact<Sym: Symbol>(
+    automaton: &Automaton<Sym>,
+    n: usize,
+) -> Automaton<Sym> {
+    if n == 0 {
+        let mut builder = AutomatonBuilder::new();
+        builder.set_accepting(START, true);
+        return builder.build();
+    }
+
+    (1..n).fold(Nfa::from_dfa(automaton).to_dfa(), |repeated, _| {
+        concat(&repeated, automaton)
+    })
+}
+
+/// Interleavings of a word from `first` with a word from `second`, failing
+/// if the determinized result would need more than `max_states` states
+pub fn shuffle<Sym: Symbol>(
+    first: &Automaton<Sym>,
+    second: &Automaton<Sym>,
+    max_states: usize,
+) -> Result<Automaton<Sym>, DeterminizationError> {
+    let width = second.states.len();
+    let mut nfa = Nfa::new();
+    nfa.set_accepting(
+        START,
+        first.states[START].accepting && second.states[START].accepting,
+    );
+
+    for (p, first_state) in first.states.iter().enumerate() {
+        for (q, second_state) in second.states.iter().enumerate() {
+            if p != START || q != START {
+                nfa.add_state(first_state.accepting && second_state.accepting);
+            }
+        }
+    }
+
+    for (p, first_state) in first.states.iter().enumerate() {
+        for (q, second_state) in second.states.iter().enumerate() {
+            let from = p * width + q;
+
+            for (range, to) in first_state.edges() {
+                nfa.add_transition(from, to * width + q, range.clone());
+            }
+
+            for (range, to) in second_state.edges() {
+                nfa.add_transition(from, p * width + to, range.clone());
+            }
+        }
+    }
+
+    nfa.to_dfa_limited(max_states)
+}
+
 #[cfg(test)]
 mod testing {
     use super::*;
@@ -180,4 +923,395 @@ mod testing {
         assert!(!automaton.is_alive());
         assert!(!automaton.is_previous_accepting());
     }
+
+    #[test]
+    fn test_validate_dead_end() {
+        let mut builder = AutomatonBuilder::new();
+        let a = builder.add_state(false);
+        let dead_end = builder.add_state(false);
+        let end = builder.add_state(true);
+        builder.add_transition(START, a, 'a'..='a');
+        builder.add_transition(a, end, 'b'..='b');
+        builder.add_transition(a, dead_end, 'c'..='c');
+
+        assert_eq!(
+            builder.validate(),
+            vec![ValidationIssue::DeadEnd(dead_end)]
+        );
+
+        let mut builder = AutomatonBuilder::new();
+        let end = builder.add_state(true);
+        builder.add_transition(START, end, 'a'..='a');
+
+        assert!(builder.validate().is_empty());
+    }
+
+    #[test]
+    fn test_negated_class() {
+        let mut builder = AutomatonBuilder::new();
+        let end = builder.add_state(true);
+        builder.add_negated_class(START, end, ['"'..='"', '\n'..='\n']);
+        let automaton = builder.build();
+
+        for c in ['a', '\0', '\u{D7FF}', '\u{E000}', '\u{1F600}', char::MAX] {
+            assert!(automaton.accepts([c]), "{:?}", c);
+        }
+
+        assert!(!automaton.accepts(['"']));
+        assert!(!automaton.accepts(['\n']));
+
+        let mut builder = AutomatonBuilder::new();
+        let end = builder.add_state(true);
+        builder.add_negated_class(START, end, ['\u{D000}'..='\u{D7FF}']);
+        let automaton = builder.build();
+
+        assert!(automaton.accepts(['\u{CFFF}']));
+        assert!(!automaton.accepts(['\u{D7FF}']));
+        assert!(automaton.accepts(['\u{E000}']));
+    }
+
+    #[test]
+    fn test_trim() {
+        let mut builder = AutomatonBuilder::new();
+        let unreachable = builder.add_state(true);
+        let c = builder.add_state(false);
+        let dead = builder.add_state(false);
+        let end = builder.add_state(true);
+        builder.add_transition(START, c, 'c'..='c');
+        builder.add_transition(c, end, 'a'..='a');
+        builder.add_transition(unreachable, dead, 'x'..='x');
+        builder.add_transition(unreachable, end, 'y'..='y');
+        let mut automaton = builder.build();
+
+        automaton.transition(Some('c'));
+        automaton.trim();
+
+        assert_eq!(automaton.states.len(), 3);
+
+        automaton.transition(Some('a'));
+        assert!(automaton.is_accepting());
+
+        for word in ["ca", "c", "", "y", "cax"] {
+            assert_eq!(automaton.accepts(word.chars()), word == "ca");
+        }
+    }
+
+    #[test]
+    fn test_validate_accepting_start() {
+        let mut builder = AutomatonBuilder::new();
+        let end = builder.add_state(true);
+        builder.add_transition(START, end, 'a'..='a');
+
+        assert_eq!(builder.validate_start(), None);
+
+        builder.set_accepting(START, true);
+
+        assert_eq!(
+            builder.validate_start(),
+            Some(ValidationIssue::AcceptingStart)
+        );
+        assert!(builder.validate().is_empty());
+        assert!(builder.build().accepts("".chars()));
+    }
+
+    #[test]
+    fn test_compact_transitions() {
+        let builder = || {
+            let mut builder = AutomatonBuilder::new();
+            let letter = builder.add_state(true);
+            let digit = builder.add_state(true);
+
+            for c in 'a'..='z' {
+                builder.add_transition(START, letter, c..=c);
+            }
+
+            builder.add_transition(START, digit, '0'..='4');
+            builder.add_transition(START, digit, '3'..='9');
+            builder
+        };
+
+        let uncompacted = builder().build();
+
+        assert_eq!(uncompacted.transition_count(), 27);
+
+        let automaton = builder().try_build().unwrap();
+
+        assert_eq!(automaton.transition_count(), 2);
+        assert!(automaton.memory_size() < uncompacted.memory_size());
+        assert!(automaton.accepts("q".chars()));
+        assert!(automaton.accepts("7".chars()));
+        assert!(!automaton.accepts("A".chars()));
+    }
+
+    #[test]
+    fn test_max_length() {
+        let mut builder = AutomatonBuilder::new();
+        let a = builder.add_state(true);
+        builder.add_transition(START, a, 'a'..='a');
+        builder.add_transition(a, a, 'a'..='a');
+        let mut automaton = builder.build().with_max_length(2);
+
+        automaton.transition(Some('a'));
+        automaton.transition(Some('a'));
+
+        assert!(automaton.is_alive());
+
+        automaton.transition(Some('a'));
+
+        assert!(!automaton.is_alive());
+        assert!(automaton.is_previous_accepting());
+
+        automaton.reset();
+        automaton.transition(Some('a'));
+
+        assert!(automaton.is_alive());
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let comment = block_comment_automaton(b"/*", b"*/");
+
+        assert!(comment.accepts(*b"/* a * b */"));
+        assert!(comment.accepts(*b"/**/"));
+        assert!(comment.accepts(*b"/* a **/"));
+        assert!(!comment.accepts(*b"/* a * b */ c"));
+        assert!(!comment.accepts(*b"/* a * b"));
+        assert!(!comment.accepts(*b"/*/"));
+
+        let mut comment = comment;
+        let mut ended_at = None;
+
+        for (idx, byte) in b"/* a * b */ x".iter().enumerate() {
+            comment.transition(Some(*byte));
+
+            if !comment.is_alive() {
+                ended_at = Some(idx);
+                break;
+            }
+        }
+
+        assert!(comment.is_previous_accepting());
+        assert_eq!(ended_at, Some(11));
+    }
+
+    #[test]
+    fn test_line_comment() {
+        let mut comment = line_comment_automaton(b"//");
+
+        assert!(comment.accepts(*b"// hello"));
+        assert!(comment.accepts(*b"//"));
+        assert!(!comment.accepts(*b"/"));
+        assert!(!comment.accepts(*b"// hi\n"));
+
+        let mut consumed = 0;
+
+        for byte in b"// hi\nx" {
+            comment.transition(Some(*byte));
+
+            if !comment.is_alive() {
+                break;
+            }
+
+            consumed += 1;
+        }
+
+        assert!(comment.is_previous_accepting());
+        assert_eq!(consumed, 5);
+    }
+
+    #[test]
+    fn test_string_literal() {
+        let string = string_literal_automaton(b'"', b'\\');
+
+        assert!(string.accepts(*b"\"a\\\"b\""));
+        assert!(string.accepts(*b"\"\""));
+        assert!(string.accepts(*b"\"\\\\\""));
+        assert!(!string.accepts(*b"\"abc"));
+        assert!(!string.accepts(*b"\"a\\\""));
+        assert!(!string.accepts(*b"\"a\"b\""));
+    }
+
+    #[test]
+    fn test_float() {
+        let float = float_automaton();
+
+        for accepted in ["3.14", "-2e10", "+.5", ".5", "42", "1.5E-3", "0e+1"] {
+            assert!(float.accepts(accepted.bytes()), "{accepted}");
+        }
+
+        for rejected in [".", "1e", "1.2.3", "1.", "+", "-.", "e5", "1e+", ""] {
+            assert!(!float.accepts(rejected.bytes()), "{rejected}");
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        let automaton = concat(
+            &keyword_automaton("ab".chars()),
+            &keyword_automaton("cd".chars()),
+        );
+
+        assert!(automaton.accepts("abcd".chars()));
+        assert!(!automaton.accepts("ab".chars()));
+        assert!(!automaton.accepts("cd".chars()));
+        assert!(!automaton.accepts("abcdcd".chars()));
+    }
+
+    #[test]
+    fn test_repeat_exact() {
+        let automaton = repeat_exact(&keyword_automaton("ab".chars()), 2);
+
+        assert!(automaton.accepts("abab".chars()));
+        assert!(!automaton.accepts("ab".chars()));
+        assert!(!automaton.accepts("ababab".chars()));
+    }
+
+    #[test]
+    fn test_repeat_exact_zero() {
+        let automaton = repeat_exact(&keyword_automaton("ab".chars()), 0);
+
+        assert!(automaton.accepts("".chars()));
+        assert!(!automaton.accepts("ab".chars()));
+    }
+
+    #[test]
+    fn test_from_glob() {
+        let rust_files = Automaton::from_glob("*.rs");
+
+        assert!(rust_files.accepts("main.rs".chars()));
+        assert!(rust_files.accepts(".rs".chars()));
+        assert!(!rust_files.accepts("main.py".chars()));
+
+        let single = Automaton::from_glob("?.txt");
+
+        assert!(single.accepts("a.txt".chars()));
+        assert!(!single.accepts("ab.txt".chars()));
+        assert!(!single.accepts(".txt".chars()));
+
+        let class = Automaton::from_glob("[abc]x[0-9]");
+
+        assert!(class.accepts("bx7".chars()));
+        assert!(!class.accepts("dx7".chars()));
+        assert!(!class.accepts("axa".chars()));
+
+        let unterminated = Automaton::from_glob("[ab");
+
+        assert!(unterminated.accepts("[ab".chars()));
+
+        let bracket = Automaton::from_glob("[]a]b");
+
+        assert!(bracket.accepts("]b".chars()));
+        assert!(bracket.accepts("ab".chars()));
+        assert!(!bracket.accepts("a]b".chars()));
+    }
+
+    #[test]
+    fn test_char_symbol_surrogate_gap() {
+        assert_eq!('\u{D7FF}'.successor(), Some('\u{E000}'));
+        assert_eq!('\u{E000}'.predecessor(), Some('\u{D7FF}'));
+        assert_eq!(char::MAX.successor(), None);
+        assert_eq!('\0'.predecessor(), None);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let letters = keyword_automaton("ab".chars());
+        let digits = keyword_automaton("12".chars());
+        let automaton = shuffle(&letters, &digits, 64).unwrap();
+
+        for word in ["ab12", "a1b2", "a12b", "1a2b", "1ab2", "12ab"] {
+            assert!(automaton.accepts(word.chars()), "{}", word);
+        }
+
+        for word in ["ba12", "a21b", "ab1", "ab122", ""] {
+            assert!(!automaton.accepts(word.chars()), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_shuffle_state_limit() {
+        let letters = keyword_automaton("ab".chars());
+        let digits = keyword_automaton("12".chars());
+
+        assert!(matches!(
+            shuffle(&letters, &digits, 4),
+            Err(DeterminizationError::TooManyStates),
+        ));
+    }
+
+    #[test]
+    fn test_many_ranges() {
+        let mut builder = AutomatonBuilder::<u8>::new();
+
+        // Added out of order, so lookup has to sort them
+        for idx in (0..60).rev() {
+            let target = builder.add_state(true);
+            builder.add_transition(START, target, idx * 4..=idx * 4 + 1);
+        }
+
+        let automaton = builder.build();
+        let start = &automaton.states[START];
+
+        for idx in 0..60u8 {
+            let target = Some(60 - usize::from(idx));
+
+            assert_eq!(start.transition(Some(idx * 4)), target);
+            assert_eq!(start.transition(Some(idx * 4 + 1)), target);
+            assert_eq!(start.transition(Some(idx * 4 + 2)), None);
+        }
+
+        assert_eq!(start.transition(Some(u8::MAX)), None);
+        assert!(automaton.accepts([8]));
+    }
+
+    #[test]
+    fn test_overlapping_transitions() {
+        let mut builder = AutomatonBuilder::<u8>::new();
+        let first = builder.add_state(true);
+        let second = builder.add_state(true);
+        builder.add_transition(START, first, b'a'..=b'm');
+        builder.add_transition(START, first, b'k'..=b'w');
+        builder.add_transition(first, second, b'a'..=b'c');
+        builder.add_transition(first, first, b'd'..=b'f');
+        builder.add_transition(first, second, b'0'..=b'9');
+        builder.add_transition(second, first, b'0'..=b'4');
+        builder.add_transition(second, second, b'4'..=b'9');
+
+        assert_eq!(
+            builder.try_build().err(),
+            Some(BuildError::OverlappingRanges {
+                state: second,
+                first: b'0'..=b'4',
+                second: b'4'..=b'9',
+            })
+        );
+
+        // Ranges that only touch are fine
+        let mut builder = AutomatonBuilder::<u8>::new();
+        let first = builder.add_state(true);
+        let second = builder.add_state(true);
+        builder.add_transition(START, first, b'a'..=b'c');
+        builder.add_transition(START, second, b'd'..=b'f');
+
+        let automaton = builder.try_build().unwrap();
+
+        assert!(automaton.accepts(*b"c"));
+        assert!(automaton.accepts(*b"d"));
+    }
+
+    #[test]
+    fn test_build_overlapping() {
+        let mut builder = AutomatonBuilder::<u8>::new();
+        let first = builder.add_state(true);
+        let second = builder.add_state(false);
+        builder.add_transition(START, first, b'a'..=b'm');
+        builder.add_transition(START, second, b'm'..=b'z');
+        let automaton = builder.build();
+
+        // The target added first wins the shared symbol
+        assert!(automaton.accepts(*b"a"));
+        assert!(automaton.accepts(*b"m"));
+        assert!(!automaton.accepts(*b"n"));
+        assert!(automaton.is_prefix(*b"n"));
+    }
 }
@@ -0,0 +1,213 @@
+use crate::brackets::BracketError;
+use crate::dfa::BuildError;
+use crate::nfa::DeterminizationError;
+use crate::persist::PersistError;
+use crate::regex::RegexError;
+use core::fmt;
+
+/// Crate-level error for drivers that mix several phases. Each variant wraps
+/// a module's own error unchanged, reachable via `source()`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    Determinization(DeterminizationError),
+    /// Failed byte `AutomatonBuilder`
+    Build(BuildError<u8>),
+    /// Failed char `AutomatonBuilder`
+    CharBuild(BuildError<char>),
+    Brackets(BracketError),
+    Persist(PersistError),
+    Regex(RegexError),
+}
+
+/// Stable discriminant of `Error`, for matching without the payload
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorKind {
+    Determinization,
+    Build,
+    Brackets,
+    Persist,
+    Regex,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Determinization(_) => ErrorKind::Determinization,
+            Self::Build(_) | Self::CharBuild(_) => ErrorKind::Build,
+            Self::Brackets(_) => ErrorKind::Brackets,
+            Self::Persist(_) => ErrorKind::Persist,
+            Self::Regex(_) => ErrorKind::Regex,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Determinization(_) => write!(f, "Failed to build automaton"),
+            Self::Build(_) | Self::CharBuild(_) => {
+                write!(f, "Invalid automaton definition")
+            }
+            Self::Brackets(_) => write!(f, "Unbalanced brackets"),
+            Self::Persist(_) => write!(f, "Failed to read persisted data"),
+            Self::Regex(_) => write!(f, "Invalid regular expression"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Determinization(err) => Some(err),
+            Self::Build(err) => Some(err),
+            Self::CharBuild(err) => Some(err),
+            Self::Brackets(err) => Some(err),
+            Self::Persist(err) => Some(err),
+            Self::Regex(err) => Some(err),
+        }
+    }
+}
+
+impl From<DeterminizationError> for Error {
+    fn from(err: DeterminizationError) -> Self {
+        Self::Determinization(err)
+    }
+}
+
+impl From<BuildError<u8>> for Error {
+    fn from(err: BuildError<u8>) -> Self {
+        Self::Build(err)
+    }
+}
+
+impl From<BuildError<char>> for Error {
+    fn from(err: BuildError<char>) -> Self {
+        Self::CharBuild(err)
+    }
+}
+
+impl From<BracketError> for Error {
+    fn from(err: BracketError) -> Self {
+        Self::Brackets(err)
+    }
+}
+
+impl From<PersistError> for Error {
+    fn from(err: PersistError) -> Self {
+        Self::Persist(err)
+    }
+}
+
+impl From<RegexError> for Error {
+    fn from(err: RegexError) -> Self {
+        Self::Regex(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_thread_safe<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn errors_are_thread_safe() {
+        assert_thread_safe::<Error>();
+        assert_thread_safe::<DeterminizationError>();
+        assert_thread_safe::<BuildError<u8>>();
+        assert_thread_safe::<BuildError<char>>();
+        assert_thread_safe::<BracketError>();
+        assert_thread_safe::<PersistError>();
+        assert_thread_safe::<RegexError>();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn build_errors_convert() {
+        use crate::dfa::{AutomatonBuilder, START};
+        use std::error::Error as _;
+
+        let mut builder = AutomatonBuilder::<char>::new();
+        let first = builder.add_state(true);
+        let second = builder.add_state(true);
+        builder.add_transition(START, first, 'a'..='m');
+        builder.add_transition(START, second, 'k'..='z');
+        let build_err = builder.try_build().unwrap_err();
+        let err = Error::from(build_err.clone());
+
+        assert_eq!(err.kind(), ErrorKind::Build);
+        assert_eq!(
+            err.source().unwrap().downcast_ref::<BuildError<char>>(),
+            Some(&build_err),
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn source_chain() {
+        use crate::regex::regex_automaton_limited;
+        use std::error::Error as _;
+
+        let compile = |pattern: &str| -> Result<(), Error> {
+            regex_automaton_limited(pattern, 8)?;
+            Ok(())
+        };
+        let err = compile("(a|b)*a(a|b)(a|b)(a|b)").unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Regex);
+
+        let regex_err = err.source().unwrap();
+        assert_eq!(
+            regex_err.downcast_ref::<RegexError>(),
+            Some(&RegexError::Determinization(
+                DeterminizationError::TooManyStates
+            )),
+        );
+
+        let determinization_err = regex_err.source().unwrap();
+        assert_eq!(
+            determinization_err.downcast_ref::<DeterminizationError>(),
+            Some(&DeterminizationError::TooManyStates),
+        );
+        assert!(determinization_err.source().is_none());
+
+        // Each level describes itself without repeating its source
+        let messages = [
+            err.to_string(),
+            regex_err.to_string(),
+            determinization_err.to_string(),
+        ];
+
+        for (idx, message) in messages.iter().enumerate() {
+            for inner in &messages[idx + 1..] {
+                assert!(!message.contains(inner.as_str()));
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn persist_source() {
+        use crate::persist::{self, Version};
+        use std::error::Error as _;
+
+        let version = Version { major: 1, minor: 0 };
+        let read = |framed: &[u8]| -> Result<(), Error> {
+            persist::read_framed(framed, 7, version)?;
+            Ok(())
+        };
+        let err = read(b"not framed").unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::Persist);
+        assert_eq!(err.to_string(), "Failed to read persisted data");
+
+        let source = err.source().unwrap();
+        assert_eq!(source.to_string(), PersistError::WrongMagic.to_string());
+        assert!(source.source().is_none());
+        assert_eq!(
+            source.downcast_ref::<PersistError>(),
+            Some(&PersistError::WrongMagic),
+        );
+    }
+}
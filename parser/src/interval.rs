@@ -0,0 +1,148 @@
+use crate::dfa::Symbol;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Sorted, non-overlapping set of symbol ranges
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct IntervalSet<Sym: Copy + Ord> {
+    ranges: Vec<RangeInclusive<Sym>>,
+}
+
+impl<Sym: Copy + Ord> IntervalSet<Sym> {
+    pub(crate) fn new() -> Self {
+        Self { ranges: Vec::new() }
+    }
+
+    pub(crate) fn ranges(&self) -> &[RangeInclusive<Sym>] {
+        &self.ranges
+    }
+
+    /// Insert `range`, merging it with any ranges it overlaps
+    pub(crate) fn insert(&mut self, range: RangeInclusive<Sym>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let (mut start, mut end) = range.into_inner();
+        let first = self.ranges.partition_point(|r| *r.end() < start);
+        let mut last = first;
+
+        while last < self.ranges.len() && *self.ranges[last].start() <= end {
+            start = start.min(*self.ranges[last].start());
+            end = end.max(*self.ranges[last].end());
+            last += 1;
+        }
+
+        self.ranges.splice(first..last, [start..=end]);
+    }
+
+    pub(crate) fn contains(&self, symbol: Sym) -> bool {
+        let idx = self.ranges.partition_point(|r| *r.end() < symbol);
+        self.ranges
+            .get(idx)
+            .is_some_and(|range| *range.start() <= symbol)
+    }
+}
+
+impl<Sym: Symbol> IntervalSet<Sym> {
+    /// Merge ranges that touch without overlapping, e.g. `a..=c` and `d..=f`
+    pub(crate) fn merge_adjacent(&mut self) {
+        let mut merged: Vec<RangeInclusive<Sym>> =
+            Vec::with_capacity(self.ranges.len());
+
+        for range in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last)
+                    if Symbol::successor(*last.end())
+                        == Some(*range.start()) =>
+                {
+                    *last = *last.start()..=*range.end();
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        self.ranges = merged;
+    }
+
+    /// Symbols of `within` not in this set
+    pub(crate) fn complement(&self, within: RangeInclusive<Sym>) -> Self {
+        let (mut start, end) = within.into_inner();
+        let mut complement = Self::new();
+
+        for range in &self.ranges {
+            if *range.end() < start {
+                continue;
+            }
+
+            if *range.start() > end {
+                break;
+            }
+
+            if let Some(before) = range.start().predecessor() {
+                if start <= before {
+                    complement.insert(start..=before.min(end));
+                }
+            }
+
+            match range.end().successor() {
+                Some(after) => start = after,
+                None => return complement,
+            }
+        }
+
+        complement.insert(start..=end);
+        complement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_merges_overlapping() {
+        let mut set = IntervalSet::new();
+        set.insert(b'm'..=b'p');
+        set.insert(b'a'..=b'c');
+        set.insert(b'x'..=b'z');
+        set.insert(b'b'..=b'n');
+
+        assert_eq!(set.ranges(), &[b'a'..=b'p', b'x'..=b'z']);
+        assert!(set.contains(b'a'));
+        assert!(set.contains(b'p'));
+        assert!(!set.contains(b'q'));
+        assert!(set.contains(b'y'));
+        assert!(!set.contains(b'0'));
+    }
+
+    #[test]
+    fn merge_adjacent_ranges() {
+        let mut set = IntervalSet::new();
+
+        for c in 'a'..='z' {
+            set.insert(c..=c);
+        }
+
+        assert_eq!(set.ranges().len(), 26);
+
+        set.merge_adjacent();
+
+        assert_eq!(set.ranges(), &['a'..='z']);
+    }
+
+    #[test]
+    fn complement_within() {
+        let mut set = IntervalSet::new();
+        set.insert(0..=3);
+        set.insert(b'a'..=b'c');
+        set.insert(250..=255);
+
+        assert_eq!(set.complement(0..=255).ranges(), &[4..=96, b'd'..=249]);
+        assert_eq!(set.complement(b'b'..=b'z').ranges(), &[b'd'..=b'z']);
+        assert_eq!(IntervalSet::new().complement(1..=2).ranges(), &[1..=2]);
+    }
+}
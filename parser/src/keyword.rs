@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+pub struct KeywordTable<K: Copy> {
+    keywords: HashMap<Vec<u8>, K>,
+}
+
+impl<K: Copy> KeywordTable<K> {
+    pub fn new<'a>(keywords: impl IntoIterator<Item = (&'a str, K)>) -> Self {
+        Self {
+            keywords: keywords
+                .into_iter()
+                .map(|(text, kind)| (text.as_bytes().to_vec(), kind))
+                .collect(),
+        }
+    }
+
+    pub fn classify(&self, text: &[u8]) -> Option<K> {
+        self.keywords.get(text).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keywords.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum TestKeyword {
+        While,
+        If,
+    }
+
+    #[test]
+    fn classify_keywords() {
+        let table = KeywordTable::new([
+            ("while", TestKeyword::While),
+            ("if", TestKeyword::If),
+        ]);
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.classify(b"while"), Some(TestKeyword::While));
+        assert_eq!(table.classify(b"if"), Some(TestKeyword::If));
+        assert_eq!(table.classify(b"foo"), None);
+        assert_eq!(table.classify(b"whil"), None);
+    }
+}
@@ -1,29 +1,370 @@
-use crate::dfa;
-use std::cell::Cell;
+use crate::dfa::{self, Symbol};
+#[cfg(feature = "serde")]
+use crate::persist::{self, PersistError, Version};
+use crate::position::{LineMapBuilder, Span};
+use crate::product::{product, shortest_accepted};
+use crate::recognizer::Recognizer;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "bumpalo")]
+use bumpalo::Bump;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "serde")]
+const LEXER_FORMAT: u16 = 1;
+#[cfg(feature = "serde")]
+const LEXER_VERSION: Version = Version { major: 2, minor: 0 };
 
 pub trait TokenKind: Copy + Eq {
     fn unknown() -> Self;
     fn has_text(&self) -> bool;
+
+    fn is_trivia(&self) -> bool {
+        false
+    }
+}
+
+/// Union of two kind enums, for lexers assembled from rules over both.
+/// Unknown tokens are reported as `Left(A::unknown())`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A: TokenKind, B: TokenKind> TokenKind for Either<A, B> {
+    fn unknown() -> Self {
+        Self::Left(A::unknown())
+    }
+
+    fn has_text(&self) -> bool {
+        match self {
+            Self::Left(kind) => kind.has_text(),
+            Self::Right(kind) => kind.has_text(),
+        }
+    }
+
+    fn is_trivia(&self) -> bool {
+        match self {
+            Self::Left(kind) => kind.is_trivia(),
+            Self::Right(kind) => kind.is_trivia(),
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Activation<K> {
+    Always,
+    After(Vec<K>),
+    AtStart,
+}
+
+/// Token iterator returned by `Lexer::lex`, which flushes the pending
+/// token once the symbols run out
+pub struct Tokens<Sym: Copy + Ord, K: TokenKind, R, I> {
+    lexer: Lexer<Sym, K, R>,
+    symbols: I,
+    flushed: bool,
+}
+
+impl<Sym, K, R, I> Iterator for Tokens<Sym, K, R, I>
+where
+    Sym: Copy + Ord,
+    K: TokenKind,
+    R: Recognizer<Sym>,
+    I: Iterator<Item = Sym>,
+{
+    type Item = Token<Sym, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for symbol in self.symbols.by_ref() {
+            if let Some(token) = self.lexer.step(Some(symbol), None) {
+                return Some(token);
+            }
+        }
+
+        if self.flushed {
+            return None;
+        }
+
+        self.flushed = true;
+        self.lexer.step(None, None)
+    }
+}
+
+/// Token iterator returned by `Lexer::lex_in`
+#[cfg(feature = "bumpalo")]
+pub struct ArenaTokens<'bump, Sym: Copy + Ord, K: TokenKind, R, I> {
+    tokens: Tokens<Sym, K, R, I>,
+    bump: &'bump Bump,
+}
+
+#[cfg(feature = "bumpalo")]
+impl<'bump, Sym, K, R, I> Iterator for ArenaTokens<'bump, Sym, K, R, I>
+where
+    Sym: Copy + Ord + 'bump,
+    K: TokenKind,
+    R: Recognizer<Sym>,
+    I: Iterator<Item = Sym>,
+{
+    type Item = ArenaToken<'bump, Sym, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.next()?;
+        let text = token.text.map(|mut text| {
+            let arena_text = &*self.bump.alloc_slice_copy(&text);
+            text.clear();
+            self.tokens.lexer.spare_text = text;
+            arena_text
+        });
+
+        Some(ArenaToken {
+            kind: token.kind,
+            text,
+            is_trivia: token.is_trivia,
+            span: token.span,
+        })
+    }
+}
+
+/// Token iterator returned by `Lexer::drive`
+pub struct Driver<Sym: Copy + Ord, K: TokenKind, R, I> {
+    lexer: Lexer<Sym, K, R>,
+    symbols: I,
+    queued: VecDeque<Token<Sym, K>>,
+    flushed: bool,
+}
+
+impl<Sym, K, R, I> Driver<Sym, K, R, I>
+where
+    Sym: Copy + Ord,
+    K: TokenKind,
+    R: Recognizer<Sym>,
+    I: Iterator<Item = Sym>,
+{
+    /// Switch to `automata` for the rest of the input. Symbols already read
+    /// past the last token are lexed again with the new automata, and the
+    /// previous token kind is kept for activation rules.
+    pub fn set_automata(&mut self, automata: Vec<(R, K)>) {
+        let pending = core::mem::take(&mut self.lexer.token_text);
+        let previous_kind = self.lexer.previous_kind;
+        let at_start = self.lexer.at_start;
+        let offset = self.lexer.offset - pending.len();
+
+        self.lexer.set_automata(automata);
+        self.lexer.previous_kind = previous_kind;
+        self.lexer.at_start = at_start;
+        self.lexer.offset = offset;
+        self.lexer.reset_automata();
+
+        for symbol in pending {
+            self.queued.extend(self.lexer.step(Some(symbol), None));
+        }
+    }
+}
+
+impl<Sym, K, R, I> Iterator for Driver<Sym, K, R, I>
+where
+    Sym: Copy + Ord,
+    K: TokenKind,
+    R: Recognizer<Sym>,
+    I: Iterator<Item = Sym>,
+{
+    type Item = Token<Sym, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.queued.pop_front() {
+            return Some(token);
+        }
+
+        for symbol in self.symbols.by_ref() {
+            if let Some(token) = self.lexer.step(Some(symbol), None) {
+                return Some(token);
+            }
+        }
+
+        if self.flushed {
+            return None;
+        }
+
+        self.flushed = true;
+        self.lexer.step(None, None)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EmitMode {
+    /// Emit the longest match once every automaton has died
+    MaximalMunch,
+    /// Emit as soon as the automaton accepts, ignoring longer matches
+    Eager,
 }
 
-pub struct Lexer<Sym: Copy + Ord, K: TokenKind> {
-    automata: Vec<(dfa::Automaton<Sym>, K)>,
+/// Scores the text of a match to break ties between automata
+pub type Scorer<Sym> = fn(&[Sym]) -> i64;
+
+/// Maximal-munch lexer over a set of recognizers, by default `Automaton`s
+#[derive(Clone)]
+pub struct Lexer<Sym: Copy + Ord, K: TokenKind, R = dfa::Automaton<Sym>> {
+    automata: Vec<(R, K)>,
     active_automata: Vec<usize>,
-    token_text: Cell<Vec<Sym>>,
+    activations: Vec<Activation<K>>,
+    emit_modes: Vec<EmitMode>,
+    scorers: Vec<Option<Scorer<Sym>>>,
+    capture_transform: Option<fn(&mut Vec<Sym>)>,
+    previous_kind: Option<K>,
+    at_start: bool,
+    emit_trivia: bool,
+    report_expected_kinds: bool,
+    token_text: Vec<Sym>,
+    /// Union of the spans of the symbols in `token_text`
+    token_span: Option<Span>,
+    /// Number of symbols consumed, which positions symbols without spans
+    offset: usize,
+    spare_text: Vec<Sym>,
 }
 
-impl<Sym: Copy + Ord, K: TokenKind> Lexer<Sym, K> {
-    pub fn new(automata: Vec<(dfa::Automaton<Sym>, K)>) -> Self {
+impl<Sym: Copy + Ord, K: TokenKind, R: Recognizer<Sym>> Lexer<Sym, K, R> {
+    pub fn new(automata: Vec<(R, K)>) -> Self {
         let active_automata = (0..automata.len()).collect();
+        let activations = vec![Activation::Always; automata.len()];
+        let emit_modes = vec![EmitMode::MaximalMunch; automata.len()];
+        let scorers = vec![None; automata.len()];
 
         Self {
             automata,
             active_automata,
-            token_text: vec![].into(),
+            activations,
+            emit_modes,
+            scorers,
+            capture_transform: None,
+            previous_kind: None,
+            at_start: true,
+            emit_trivia: false,
+            report_expected_kinds: false,
+            token_text: Vec::new(),
+            token_span: None,
+            offset: 0,
+            spare_text: Vec::new(),
+        }
+    }
+
+    /// Only activate automata producing `kind` when the previously emitted
+    /// token's kind is one of `previous`
+    pub fn activate_after(
+        self,
+        kind: K,
+        previous: impl IntoIterator<Item = K>,
+    ) -> Self {
+        let previous: Vec<K> = previous.into_iter().collect();
+        self.set_activation(kind, Activation::After(previous))
+    }
+
+    /// Only activate automata producing `kind` for the first token of the
+    /// input, e.g. for a `#!` line
+    pub fn anchor_at_start(self, kind: K) -> Self {
+        self.set_activation(kind, Activation::AtStart)
+    }
+
+    fn set_activation(mut self, kind: K, activation: Activation<K>) -> Self {
+        for (idx, (_, automaton_kind)) in self.automata.iter().enumerate() {
+            if *automaton_kind == kind {
+                self.activations[idx] = activation.clone();
+            }
+        }
+
+        self.reset_automata();
+        self
+    }
+
+    /// Replace the automata set, keeping the token text buffer's allocation.
+    /// Activation rules are cleared, since they refer to the old set.
+    pub fn set_automata(&mut self, automata: Vec<(R, K)>) {
+        self.activations = vec![Activation::Always; automata.len()];
+        self.emit_modes = vec![EmitMode::MaximalMunch; automata.len()];
+        self.scorers = vec![None; automata.len()];
+        self.automata = automata;
+        self.previous_kind = None;
+        self.at_start = true;
+        self.token_text.clear();
+        self.token_span = None;
+        self.offset = 0;
+        self.reset_automata();
+    }
+
+    /// Set how tokens of `kind` are emitted
+    pub fn emit_mode(mut self, kind: K, mode: EmitMode) -> Self {
+        for (idx, (_, automaton_kind)) in self.automata.iter().enumerate() {
+            if *automaton_kind == kind {
+                self.emit_modes[idx] = mode;
+            }
+        }
+
+        self
+    }
+
+    /// Break ties between automata accepting the same text by `score`,
+    /// highest first, instead of by order. Automata without a score count
+    /// as 0, and equal scores fall back to order.
+    pub fn score(mut self, kind: K, score: Scorer<Sym>) -> Self {
+        for (idx, (_, automaton_kind)) in self.automata.iter().enumerate() {
+            if *automaton_kind == kind {
+                self.scorers[idx] = Some(score);
+            }
+        }
+
+        self
+    }
+
+    /// Apply `transform` to the text of each token that keeps its text,
+    /// e.g. to lowercase identifiers. Matching and scoring still see the
+    /// original symbols.
+    pub fn capture_transform(mut self, transform: fn(&mut Vec<Sym>)) -> Self {
+        self.capture_transform = Some(transform);
+        self
+    }
+
+    /// Emit trivia tokens (flagged via `Token::is_trivia`) instead of
+    /// dropping them
+    pub fn emit_trivia(mut self, emit_trivia: bool) -> Self {
+        self.emit_trivia = emit_trivia;
+        self
+    }
+
+    /// Record on each unknown token the kinds of the automata that were
+    /// still alive when lexing failed, see `Token::expected_kinds`
+    pub fn report_expected_kinds(mut self, report: bool) -> Self {
+        self.report_expected_kinds = report;
+        self
+    }
+
+    fn is_activated(&self, idx: usize) -> bool {
+        match &self.activations[idx] {
+            Activation::Always => true,
+            Activation::After(previous) => self
+                .previous_kind
+                .map(|kind| previous.contains(&kind))
+                .unwrap_or(false),
+            Activation::AtStart => self.at_start,
         }
     }
 
-    fn step(&mut self, symbol: Option<Sym>) -> Option<Token<Sym, K>> {
+    /// Feed one symbol, or flush on `None`. `span` is the symbol's position
+    /// in the source, for inputs that carry one; otherwise the symbol spans
+    /// its index.
+    fn step(
+        &mut self,
+        symbol: Option<Sym>,
+        span: Option<Span>,
+    ) -> Option<Token<Sym, K>> {
+        // An eager match completed by the symbol that ended the previous
+        // token is emitted one step late, as only one token fits per step
+        let mut token = self.take_eager_token();
+
         self.active_automata
             .retain(|idx| self.automata[*idx].0.is_alive());
 
@@ -35,40 +376,142 @@ impl<Sym: Copy + Ord, K: TokenKind> Lexer<Sym, K> {
             any_alive = any_alive || automaton.is_alive();
         }
 
-        let mut token = None;
-
         if !any_alive {
-            for idx in &self.active_automata {
-                let (ref automaton, token_kind) = &self.automata[*idx];
+            if let Some(idx) = self.accepted_automaton() {
+                token = Some(self.token(self.automata[idx].1));
+            }
 
-                if automaton.is_previous_accepting() {
-                    token = Some(Token::new(
-                        *token_kind,
-                        self.token_text.replace(vec![]),
-                    ));
+            if token.is_none() && !self.token_text.is_empty() {
+                let mut unknown = self.token(K::unknown());
 
-                    break;
+                if self.report_expected_kinds {
+                    unknown.expected_kinds = self.expected_kinds();
                 }
+
+                token = Some(unknown);
             }
 
-            if token.is_none() {
-                token = Some(Token::new(
-                    K::unknown(),
-                    self.token_text.replace(vec![]),
-                ))
+            if let Some(kind) = token.as_ref().map(|t| t.kind) {
+                if !kind.is_trivia() {
+                    self.previous_kind = Some(kind);
+                }
             }
 
+            self.at_start = false;
+
             self.reset_automata();
-            
-            for (automaton, _) in &mut self.automata {
-                automaton.transition(symbol);
+
+            for idx in &self.active_automata {
+                self.automata[*idx].0.transition(symbol);
             }
         }
 
         if let Some(sym) = symbol {
-            self.token_text.get_mut().push(sym);
+            let span = span.unwrap_or(Span {
+                start: self.offset,
+                end: self.offset + 1,
+            });
+
+            self.token_text.push(sym);
+            self.offset += 1;
+            self.token_span = Some(match self.token_span {
+                Some(token_span) => token_span.union(span),
+                None => span,
+            });
+        }
+
+        if token.is_none() {
+            token = self.take_eager_token();
+        }
+
+        token.filter(|t| self.emit_trivia || !t.is_trivia)
+    }
+
+    /// The automaton whose match is emitted: the first to accept, unless
+    /// several accept and scoring picks a higher-scoring one
+    fn accepted_automaton(&self) -> Option<usize> {
+        let mut accepted = self
+            .active_automata
+            .iter()
+            .copied()
+            .filter(|idx| self.automata[*idx].0.is_previous_accepting());
+        let first = accepted.next()?;
+
+        if accepted.clone().next().is_none() {
+            return Some(first);
+        }
+
+        let score = |idx: usize| {
+            self.scorers[idx].map_or(0, |score| score(&self.token_text))
+        };
+        let (best, _) = accepted.fold((first, score(first)), |best, idx| {
+            let score = score(idx);
+            if score > best.1 {
+                (idx, score)
+            } else {
+                best
+            }
+        });
+
+        Some(best)
+    }
+
+    /// Kinds of the active automata, which were alive before the current
+    /// symbol
+    fn expected_kinds(&self) -> Vec<K> {
+        let mut kinds = Vec::new();
+
+        for idx in &self.active_automata {
+            let kind = self.automata[*idx].1;
+
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+
+        kinds
+    }
+
+    fn take_eager_token(&mut self) -> Option<Token<Sym, K>> {
+        let kind = self.active_automata.iter().find_map(|idx| {
+            let (automaton, kind) = &self.automata[*idx];
+            let eager = self.emit_modes[*idx] == EmitMode::Eager;
+            (eager && automaton.is_accepting()).then_some(*kind)
+        })?;
+
+        if !kind.is_trivia() {
+            self.previous_kind = Some(kind);
         }
 
+        self.at_start = false;
+        self.reset_automata();
+
+        Some(self.token(kind))
+    }
+
+    /// Token for the pending text. Kept text is swapped for the spare
+    /// buffer, which `lex_in` refills once it has copied the text out.
+    fn token(&mut self, kind: K) -> Token<Sym, K> {
+        let text = if kind.has_text() {
+            let spare = core::mem::take(&mut self.spare_text);
+            let mut text = core::mem::replace(&mut self.token_text, spare);
+
+            if let Some(transform) = self.capture_transform {
+                transform(&mut text);
+            }
+
+            text
+        } else {
+            self.token_text.clear();
+            Vec::new()
+        };
+
+        let mut token = Token::new(kind, text);
+        // Only an empty match, e.g. an eager one, leaves no span behind
+        token.span = self.token_span.take().unwrap_or(Span {
+            start: self.offset,
+            end: self.offset,
+        });
         token
     }
 
@@ -76,29 +519,516 @@ impl<Sym: Copy + Ord, K: TokenKind> Lexer<Sym, K> {
         self.automata
             .iter_mut()
             .for_each(|(automaton, _)| automaton.reset());
-        self.active_automata = (0..self.automata.len()).collect();
+        let mut active_automata = core::mem::take(&mut self.active_automata);
+        active_automata.clear();
+        active_automata.extend(
+            (0..self.automata.len()).filter(|idx| self.is_activated(*idx)),
+        );
+        self.active_automata = active_automata;
+    }
+
+    pub fn lex<I: Iterator<Item = Sym>>(
+        self,
+        symbols: I,
+    ) -> Tokens<Sym, K, R, I> {
+        Tokens {
+            lexer: self,
+            symbols,
+            flushed: false,
+        }
+    }
+
+    /// Like `lex`, but token text is copied into `bump` rather than kept in
+    /// a `Vec` per token, so texts are freed all at once with the arena
+    #[cfg(feature = "bumpalo")]
+    pub fn lex_in<'bump, I: Iterator<Item = Sym>>(
+        self,
+        symbols: I,
+        bump: &'bump Bump,
+    ) -> ArenaTokens<'bump, Sym, K, R, I> {
+        ArenaTokens {
+            tokens: self.lex(symbols),
+            bump,
+        }
+    }
+
+    /// Like `lex`, for symbols that carry their own source spans, e.g. from
+    /// a preprocessing stage. Each token's span covers the spans of the
+    /// symbols it consumed, gaps included.
+    pub fn lex_spanned(
+        mut self,
+        symbols: impl Iterator<Item = (Sym, Span)>,
+    ) -> impl Iterator<Item = Token<Sym, K>> {
+        symbols
+            .map(|(symbol, span)| (Some(symbol), Some(span)))
+            .chain(Some((None, None)))
+            .flat_map(move |(symbol, span)| self.step(symbol, span))
     }
 
-    pub fn lex(
+    /// Like `lex`, but every `None` in `symbols` flushes the pending token,
+    /// and nothing is flushed at the end. For streaming input, e.g. a REPL
+    /// that flushes at each line it reads.
+    pub fn lex_with_flushes(
         mut self,
         symbols: impl Iterator<Item = Option<Sym>>,
     ) -> impl Iterator<Item = Token<Sym, K>> {
-        symbols.flat_map(move |symbol| self.step(symbol))
+        symbols.flat_map(move |symbol| self.step(symbol, None))
+    }
+
+    /// Like `lex`, but the returned driver allows replacing the automata
+    /// between tokens, e.g. when a directive token changes the rules for
+    /// the rest of the input
+    pub fn drive<I: Iterator<Item = Sym>>(
+        self,
+        symbols: I,
+    ) -> Driver<Sym, K, R, I> {
+        Driver {
+            lexer: self,
+            symbols,
+            queued: VecDeque::new(),
+            flushed: false,
+        }
+    }
+
+    /// Lex all of `symbols`, then return the tokens last to first
+    pub fn lex_reversed(
+        self,
+        symbols: impl Iterator<Item = Sym>,
+    ) -> Vec<Token<Sym, K>> {
+        let mut tokens: Vec<_> = self.lex(symbols).collect();
+        tokens.reverse();
+        tokens
+    }
+
+    /// Like `lex`, but reports a `TraceEntry` to `tracer` for every symbol
+    pub fn lex_traced(
+        mut self,
+        symbols: impl Iterator<Item = Sym>,
+        mut tracer: impl FnMut(TraceEntry<Sym, K>),
+    ) -> impl Iterator<Item = Token<Sym, K>> {
+        symbols.map(Some).chain(Some(None)).flat_map(move |symbol| {
+            let before = self.alive_automata();
+            let token = self.step(symbol, None);
+            let alive = self.alive_automata();
+            let died = before
+                .into_iter()
+                .filter(|idx| token.is_some() || !alive.contains(idx))
+                .collect();
+
+            tracer(TraceEntry {
+                symbol,
+                alive,
+                died,
+                emitted: token.as_ref().map(|t| t.kind),
+            });
+
+            token
+        })
+    }
+
+    fn alive_automata(&self) -> Vec<usize> {
+        self.active_automata
+            .iter()
+            .copied()
+            .filter(|idx| self.automata[*idx].0.is_alive())
+            .collect()
+    }
+}
+
+impl<Sym, K, R> Lexer<Sym, K, R>
+where
+    Sym: Copy + Ord,
+    K: TokenKind,
+    R: Recognizer<Sym> + Clone,
+{
+    /// Run every automaton on its own over `input` and report how far each
+    /// got, in the order they were passed to `new`. Activation rules are
+    /// ignored.
+    pub fn diagnose(&self, input: &[Sym]) -> Vec<(K, MatchResult)> {
+        self.automata
+            .iter()
+            .map(|(automaton, kind)| {
+                let mut automaton = automaton.clone();
+                automaton.reset();
+                (*kind, match_result(&mut automaton, input))
+            })
+            .collect()
+    }
+}
+
+fn match_result<Sym: Copy>(
+    automaton: &mut impl Recognizer<Sym>,
+    input: &[Sym],
+) -> MatchResult {
+    let mut longest = automaton.is_accepting().then_some(0);
+
+    for (idx, symbol) in input.iter().enumerate() {
+        automaton.transition(Some(*symbol));
+
+        if !automaton.is_alive() {
+            return match longest {
+                Some(len) => MatchResult::Prefix(len),
+                None => MatchResult::DiedAt(idx),
+            };
+        }
+
+        if automaton.is_accepting() {
+            longest = Some(idx + 1);
+        }
+    }
+
+    match longest {
+        Some(len) if len == input.len() => MatchResult::Full,
+        Some(len) => MatchResult::Prefix(len),
+        None => MatchResult::PartialThenNeedMore,
+    }
+}
+
+impl<Sym: Copy + Ord, K: TokenKind> Lexer<Sym, K> {
+    /// Classify `input` as a complete token, a prefix of one, or neither,
+    /// preferring automata in the order they were passed to `new`
+    pub fn classify_partial(&self, input: &[Sym]) -> PartialMatch<K> {
+        let mut partial = PartialMatch::Invalid;
+
+        for (automaton, kind) in &self.automata {
+            if automaton.accepts(input.iter().copied()) {
+                return PartialMatch::Complete(*kind);
+            }
+
+            if automaton.is_prefix(input.iter().copied()) {
+                partial = PartialMatch::Incomplete;
+            }
+        }
+
+        partial
+    }
+}
+
+impl<Sym: Symbol, K: TokenKind> Lexer<Sym, K> {
+    /// Pairs of automata with different kinds that accept a common string
+    /// over `alphabet`, so that their order decides which kind is emitted.
+    /// Activation rules are not taken into account.
+    pub fn check_ambiguity(
+        &self,
+        alphabet: core::ops::RangeInclusive<Sym>,
+    ) -> Vec<Ambiguity<Sym, K>> {
+        let mut ambiguities = Vec::new();
+
+        for (first, (first_automaton, first_kind)) in
+            self.automata.iter().enumerate()
+        {
+            for (second, (second_automaton, second_kind)) in
+                self.automata.iter().enumerate().skip(first + 1)
+            {
+                if first_kind == second_kind {
+                    continue;
+                }
+
+                let both = product(
+                    first_automaton,
+                    second_automaton,
+                    alphabet.clone(),
+                    |a, b| a && b,
+                );
+                let Some(example) = shortest_accepted(&both) else {
+                    continue;
+                };
+
+                let first_contained = first_automaton
+                    .is_subset_of(second_automaton, alphabet.clone());
+                let second_contained = second_automaton
+                    .is_subset_of(first_automaton, alphabet.clone());
+                let overlap = match (first_contained, second_contained) {
+                    (true, true) => Overlap::Equivalent,
+                    (false, true) => Overlap::Shadowed,
+                    (true, false) => Overlap::Contained,
+                    (false, false) => Overlap::Partial,
+                };
+
+                ambiguities.push(Ambiguity {
+                    first: (first, *first_kind),
+                    second: (second, *second_kind),
+                    overlap,
+                    example,
+                });
+            }
+        }
+
+        ambiguities
+    }
+}
+
+#[cfg(feature = "serde")]
+type LexerTables<Sym, K> = (
+    Vec<(dfa::Automaton<Sym>, K)>,
+    Vec<Activation<K>>,
+    Vec<EmitMode>,
+    bool,
+    bool,
+);
+
+#[cfg(feature = "serde")]
+impl<Sym, K> Lexer<Sym, K>
+where
+    Sym: Copy + Ord + Serialize + DeserializeOwned,
+    K: TokenKind + Serialize + DeserializeOwned,
+{
+    /// Serialize the rule set (automata, kinds, activations, emit modes and
+    /// the trivia and expected-kind flags) into a framed binary blob; run
+    /// state is not kept. Scorers and the capture transform are function
+    /// pointers and are not kept either, so a lexer using them must have
+    /// them set again after `from_bytes` to produce the same tokens.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tables = (
+            &self.automata,
+            &self.activations,
+            &self.emit_modes,
+            self.emit_trivia,
+            self.report_expected_kinds,
+        );
+        let payload = postcard::to_allocvec(&tables)
+            .expect("Serializing to a Vec cannot fail");
+
+        persist::write_framed(LEXER_FORMAT, LEXER_VERSION, &payload)
     }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PersistError> {
+        let payload = persist::read_framed(bytes, LEXER_FORMAT, LEXER_VERSION)?;
+        let tables: LexerTables<Sym, K> = postcard::from_bytes(payload)
+            .map_err(|_| PersistError::CorruptPayload)?;
+        let (
+            automata,
+            activations,
+            emit_modes,
+            emit_trivia,
+            report_expected_kinds,
+        ) = tables;
+
+        if activations.len() != automata.len()
+            || emit_modes.len() != automata.len()
+        {
+            return Err(PersistError::CorruptPayload);
+        }
+
+        let mut lexer = Self::new(automata)
+            .emit_trivia(emit_trivia)
+            .report_expected_kinds(report_expected_kinds);
+        lexer.activations = activations;
+        lexer.emit_modes = emit_modes;
+        lexer.reset_automata();
+        Ok(lexer)
+    }
+}
+
+impl<K: TokenKind, R: Recognizer<u8>> Lexer<u8, K, R> {
+    /// Lex `source`, pairing each token with the text of the line it starts
+    /// on (without the newline)
+    pub fn lex_lines<'a>(
+        self,
+        source: &'a str,
+    ) -> impl Iterator<Item = (Token<u8, K>, &'a str)> + 'a
+    where
+        K: 'a,
+        R: 'a,
+    {
+        let mut builder = LineMapBuilder::new(b'\n');
+        builder.track(source.bytes()).for_each(drop);
+        let line_map = builder.finish();
+
+        self.lex(source.bytes()).map(move |token| {
+            let (line, _) = line_map.position(token.span().start);
+            let range = line_map.line_range(line, source.len()).unwrap();
+
+            (token, &source[range])
+        })
+    }
+}
+
+/// Merge runs of adjacent tokens of `kind` into single tokens, concatenating
+/// their text
+pub fn coalesce_kind<Sym: Copy + Ord, K: TokenKind>(
+    tokens: impl Iterator<Item = Token<Sym, K>>,
+    kind: K,
+) -> impl Iterator<Item = Token<Sym, K>> {
+    let mut tokens = tokens.peekable();
+
+    core::iter::from_fn(move || {
+        let mut token = tokens.next()?;
+
+        if token.kind == kind {
+            while let Some(next) = tokens.next_if(|t| t.kind == kind) {
+                token.span = token.span.union(next.span);
+
+                if let (Some(text), Some(next_text)) =
+                    (&mut token.text, next.text)
+                {
+                    text.extend(next_text);
+                }
+            }
+        }
+
+        Some(token)
+    })
+}
+
+/// Pair each token with the kinds of its neighbours, buffering one token of
+/// lookahead
+pub fn windows<Sym: Copy + Ord, K: TokenKind>(
+    tokens: impl Iterator<Item = Token<Sym, K>>,
+) -> impl Iterator<Item = (Option<K>, Token<Sym, K>, Option<K>)> {
+    let mut tokens = tokens.peekable();
+    let mut previous_kind = None;
+
+    core::iter::from_fn(move || {
+        let token = tokens.next()?;
+        let next_kind = tokens.peek().map(|t| t.kind);
+        let prev_kind = previous_kind.replace(token.kind);
+
+        Some((prev_kind, token, next_kind))
+    })
+}
+
+/// Two automata, as index and kind in the order passed to `Lexer::new`,
+/// whose languages overlap
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ambiguity<Sym, K> {
+    pub first: (usize, K),
+    pub second: (usize, K),
+    pub overlap: Overlap,
+    /// Shortest string accepted by both
+    pub example: Vec<Sym>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overlap {
+    /// Both accept the same language, so the second never wins
+    Equivalent,
+    /// The first accepts all the second does, so the second never wins
+    Shadowed,
+    /// The second accepts all the first does, e.g. identifiers listed
+    /// after a keyword
+    Contained,
+    /// Each accepts strings the other does not
+    Partial,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PartialMatch<K> {
+    /// Some automaton accepts the input as a whole token
+    Complete(K),
+    /// No automaton accepts the input, but some could after more symbols
+    Incomplete,
+    Invalid,
+}
+
+/// How far a single automaton got over an input, see `Lexer::diagnose`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchResult {
+    /// Accepts the whole input
+    Full,
+    /// Accepts at most the first `n` symbols
+    Prefix(usize),
+    /// Still alive after the whole input without having accepted
+    PartialThenNeedMore,
+    /// Died on the symbol at this index without having accepted
+    DiedAt(usize),
+}
+
+/// Record of a single lexer step. `alive` and `died` hold automaton indices
+/// in the order they were passed to `Lexer::new`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraceEntry<Sym, K> {
+    pub symbol: Option<Sym>,
+    pub alive: Vec<usize>,
+    pub died: Vec<usize>,
+    pub emitted: Option<K>,
 }
 
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Token<Sym: Copy + Ord, K: TokenKind> {
     kind: K,
     text: Option<Vec<Sym>>,
+    is_trivia: bool,
+    expected_kinds: Vec<K>,
+    span: Span,
 }
 
 impl<Sym: Copy + Ord, K: TokenKind> Token<Sym, K> {
-    fn new(kind: K, text: Vec<Sym>) -> Self {
+    /// Token as the lexer would emit it, e.g. to test a parser without
+    /// lexing. `text` is dropped for kinds without text.
+    pub fn new(kind: K, text: Vec<Sym>) -> Self {
         Token {
             kind,
             text: if kind.has_text() { Some(text) } else { None },
+            is_trivia: kind.is_trivia(),
+            expected_kinds: Vec::new(),
+            span: Span { start: 0, end: 0 },
         }
     }
+
+    pub fn kind(&self) -> K {
+        self.kind
+    }
+
+    pub fn text(&self) -> Option<&[Sym]> {
+        self.text.as_deref()
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        self.is_trivia
+    }
+
+    /// Source range covered by the token's symbols: their indices in the
+    /// input, or the union of their spans with `Lexer::lex_spanned`
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Set the span of a token made with `new`, which starts out empty at 0
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Kind and owned text, for consuming the text without cloning
+    pub fn into_parts(self) -> (K, Option<Vec<Sym>>) {
+        (self.kind, self.text)
+    }
+
+    /// For unknown tokens from a lexer with `report_expected_kinds` set, the
+    /// kinds that could still have matched before lexing failed
+    pub fn expected_kinds(&self) -> &[K] {
+        &self.expected_kinds
+    }
+}
+
+/// Token from `Lexer::lex_in`, with its text in the arena
+#[cfg(feature = "bumpalo")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ArenaToken<'bump, Sym, K> {
+    kind: K,
+    text: Option<&'bump [Sym]>,
+    is_trivia: bool,
+    span: Span,
+}
+
+#[cfg(feature = "bumpalo")]
+impl<'bump, Sym, K: TokenKind> ArenaToken<'bump, Sym, K> {
+    pub fn kind(&self) -> K {
+        self.kind
+    }
+
+    pub fn text(&self) -> Option<&'bump [Sym]> {
+        self.text
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        self.is_trivia
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
 }
 
 #[cfg(test)]
@@ -143,9 +1073,88 @@ mod tests {
         ident_builder.build()
     }
 
-    #[test]
-    fn get_tokens() {
-        let while_dfa = dfa::keyword_automaton(*b"while");
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    enum SlashTokenKind {
+        Number,
+        Div,
+        Paren,
+        Regex,
+        Unknown,
+    }
+
+    impl TokenKind for SlashTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            matches!(self, Self::Number | Self::Regex)
+        }
+    }
+
+    fn slash_lexer() -> Lexer<u8, SlashTokenKind> {
+        let mut number_builder = dfa::AutomatonBuilder::<u8>::new();
+        let digits = number_builder.add_state(true);
+        number_builder.add_transition(dfa::START, digits, b'0'..=b'9');
+        number_builder.add_transition(digits, digits, b'0'..=b'9');
+
+        let mut regex_builder = dfa::AutomatonBuilder::<u8>::new();
+        let body = regex_builder.add_state(false);
+        let end = regex_builder.add_state(true);
+        regex_builder.add_transition(dfa::START, body, b'/'..=b'/');
+        regex_builder.add_transition(body, body, 0..=(b'/' - 1));
+        regex_builder.add_transition(body, body, (b'/' + 1)..=u8::MAX);
+        regex_builder.add_transition(body, end, b'/'..=b'/');
+
+        Lexer::new(vec![
+            (number_builder.build(), SlashTokenKind::Number),
+            (dfa::keyword_automaton(*b"/"), SlashTokenKind::Div),
+            (dfa::keyword_automaton(*b"("), SlashTokenKind::Paren),
+            (regex_builder.build(), SlashTokenKind::Regex),
+        ])
+        .activate_after(SlashTokenKind::Regex, [SlashTokenKind::Paren])
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn lexer_round_trip() {
+        let kinds = |lexer: Lexer<u8, SlashTokenKind>, source: &str| {
+            let byte_iter = source.bytes();
+            lexer
+                .lex(byte_iter)
+                .map(|t| (t.expected_kinds().to_vec(), t.into_parts()))
+                .collect::<Vec<_>>()
+        };
+        let lexer = slash_lexer()
+            .emit_mode(SlashTokenKind::Paren, EmitMode::Eager)
+            .emit_trivia(true)
+            .report_expected_kinds(true);
+        let bytes = lexer.to_bytes();
+        let loaded = Lexer::from_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            kinds(loaded.clone(), "(/a")[1].0,
+            vec![SlashTokenKind::Regex]
+        );
+
+        for source in ["1/2", "(/a/", "/a/ 12(3", "(/a"] {
+            assert_eq!(
+                kinds(loaded.clone(), source),
+                kinds(lexer.clone(), source)
+            );
+        }
+
+        assert_eq!(
+            Lexer::<u8, SlashTokenKind>::from_bytes(&bytes[..bytes.len() - 1])
+                .err(),
+            Some(PersistError::CorruptPayload)
+        );
+    }
+
+    #[test]
+    fn get_tokens() {
+        let while_dfa = dfa::keyword_automaton(*b"while");
         let if_dfa = dfa::keyword_automaton(*b"if");
         let paren_dfa = dfa::keyword_automaton(*b"(");
 
@@ -156,13 +1165,10 @@ mod tests {
             (ident_dfa(), TestLexerTokenKind::Ident),
         ]);
 
-        let byte_iter = "if  while _neat1(cool 123f"
-            .bytes()
-            .map(|b| Some(b))
-            .chain(Some(None));
+        let byte_iter = "if  while _neat1(cool 123f".bytes();
 
         let mut token_iter = lexer.lex(byte_iter);
-        let get_kind = |t: Token<_, _>| t.kind;
+        let get_kind = |t: Token<_, _>| t.kind();
 
         assert_eq!(
             token_iter.next().map(get_kind),
@@ -221,19 +1227,1029 @@ mod tests {
 
     #[test]
     fn get_ident() {
-        let lexer = Lexer::new(vec![
-            (ident_dfa(), TestLexerTokenKind::Ident)
-        ]);
+        let lexer = Lexer::new(vec![(ident_dfa(), TestLexerTokenKind::Ident)]);
 
-        let byte_iter = "_hello123"
-            .bytes()
-            .map(|b| Some(b))
-            .chain(Some(None));
+        let byte_iter = "_hello123".bytes();
 
         let mut token_iter = lexer.lex(byte_iter);
         let token = token_iter.next().unwrap();
-        
-        assert_eq!(token.kind, TestLexerTokenKind::Ident);
-        assert_eq!(token.text, Some("_hello123".bytes().collect()));
+
+        assert_eq!(token.kind(), TestLexerTokenKind::Ident);
+        assert_eq!(token.text(), Some(&b"_hello123"[..]));
+    }
+
+    #[test]
+    fn unmatched_first_symbol() {
+        let lexer = Lexer::new(vec![(ident_dfa(), TestLexerTokenKind::Ident)]);
+
+        let byte_iter = "?ab".bytes();
+        let kinds = lexer.lex(byte_iter).map(|t| t.kind()).collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            vec![TestLexerTokenKind::Unknown, TestLexerTokenKind::Ident]
+        );
+    }
+
+    #[test]
+    fn swap_automata() {
+        let kinds = |lexer: Lexer<u8, TestLexerTokenKind>| {
+            let byte_iter = "if iffy".bytes();
+            lexer.lex(byte_iter).map(|t| t.kind()).collect::<Vec<_>>()
+        };
+        let keywords =
+            || vec![(dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If)];
+        let mut lexer = Lexer::new(keywords());
+
+        assert_eq!(
+            kinds(Lexer::new(keywords())),
+            [
+                TestLexerTokenKind::If,
+                TestLexerTokenKind::Unknown,
+                TestLexerTokenKind::If,
+                TestLexerTokenKind::Unknown,
+                TestLexerTokenKind::Unknown,
+            ],
+        );
+
+        lexer.token_text.reserve(64);
+        let capacity = lexer.token_text.capacity();
+        let mut automata = keywords();
+        automata.push((ident_dfa(), TestLexerTokenKind::Ident));
+        lexer.set_automata(automata);
+
+        assert_eq!(lexer.token_text.capacity(), capacity);
+        assert_eq!(
+            kinds(lexer),
+            [
+                TestLexerTokenKind::If,
+                TestLexerTokenKind::Unknown,
+                TestLexerTokenKind::Ident,
+            ],
+        );
+    }
+
+    #[test]
+    fn activate_after_previous_kind() {
+        let kinds = |source: &str| {
+            let byte_iter = source.bytes();
+
+            slash_lexer()
+                .lex(byte_iter)
+                .map(|t| t.kind())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds("1/2"),
+            vec![
+                SlashTokenKind::Number,
+                SlashTokenKind::Div,
+                SlashTokenKind::Number
+            ]
+        );
+        assert_eq!(
+            kinds("(/a/"),
+            vec![SlashTokenKind::Paren, SlashTokenKind::Regex]
+        );
+        assert_eq!(
+            kinds("/a/"),
+            vec![
+                SlashTokenKind::Div,
+                SlashTokenKind::Unknown,
+                SlashTokenKind::Div
+            ]
+        );
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum TriviaTokenKind {
+        If,
+        Whitespace,
+        Unknown,
+    }
+
+    impl TokenKind for TriviaTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            false
+        }
+
+        fn is_trivia(&self) -> bool {
+            self == &Self::Whitespace
+        }
+    }
+
+    fn trivia_lexer() -> Lexer<u8, TriviaTokenKind> {
+        let mut whitespace_builder = dfa::AutomatonBuilder::<u8>::new();
+        let spaces = whitespace_builder.add_state(true);
+        whitespace_builder.add_transition(dfa::START, spaces, b' '..=b' ');
+        whitespace_builder.add_transition(spaces, spaces, b' '..=b' ');
+
+        Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TriviaTokenKind::If),
+            (whitespace_builder.build(), TriviaTokenKind::Whitespace),
+        ])
+    }
+
+    #[test]
+    fn trivia_dropped_by_default() {
+        let byte_iter = "if  if".bytes();
+        let kinds: Vec<_> =
+            trivia_lexer().lex(byte_iter).map(|t| t.kind()).collect();
+
+        assert_eq!(kinds, vec![TriviaTokenKind::If, TriviaTokenKind::If]);
+    }
+
+    #[test]
+    fn trivia_emitted_with_flag() {
+        let byte_iter = "if  if".bytes();
+        let tokens: Vec<_> = trivia_lexer()
+            .emit_trivia(true)
+            .lex(byte_iter)
+            .map(|t| (t.kind(), t.is_trivia()))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TriviaTokenKind::If, false),
+                (TriviaTokenKind::Whitespace, true),
+                (TriviaTokenKind::If, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn max_length_caps_match() {
+        let mut field_builder = dfa::AutomatonBuilder::<u8>::new();
+        let field = field_builder.add_state(true);
+        field_builder.add_transition(dfa::START, field, b'a'..=b'a');
+        field_builder.add_transition(field, field, b'a'..=b'a');
+
+        let lexer = Lexer::new(vec![(
+            field_builder.build().with_max_length(3),
+            TestLexerTokenKind::Ident,
+        )]);
+
+        let byte_iter = "aaaaa".bytes();
+        let texts: Vec<_> = lexer
+            .lex(byte_iter)
+            .map(|t| t.text().unwrap().to_vec())
+            .collect();
+
+        assert_eq!(texts, vec![b"aaa".to_vec(), b"aa".to_vec()]);
+    }
+
+    #[test]
+    fn trace_steps() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (dfa::keyword_automaton(*b"in"), TestLexerTokenKind::While),
+        ]);
+
+        let mut trace = vec![];
+        let byte_iter = "if".bytes();
+        let kinds: Vec<_> = lexer
+            .lex_traced(byte_iter, |entry| trace.push(entry))
+            .map(|t| t.kind())
+            .collect();
+
+        assert_eq!(kinds, vec![TestLexerTokenKind::If]);
+        assert_eq!(
+            trace,
+            vec![
+                TraceEntry {
+                    symbol: Some(b'i'),
+                    alive: vec![0, 1],
+                    died: vec![],
+                    emitted: None,
+                },
+                TraceEntry {
+                    symbol: Some(b'f'),
+                    alive: vec![0],
+                    died: vec![1],
+                    emitted: None,
+                },
+                TraceEntry {
+                    symbol: None,
+                    alive: vec![],
+                    died: vec![0],
+                    emitted: Some(TestLexerTokenKind::If),
+                },
+            ]
+        );
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum ShebangTokenKind {
+        Shebang,
+        Hash,
+        Ident,
+        Unknown,
+    }
+
+    impl TokenKind for ShebangTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            self != &Self::Hash
+        }
+    }
+
+    #[test]
+    fn anchored_at_start() {
+        let mut shebang_builder = dfa::AutomatonBuilder::<u8>::new();
+        let hash = shebang_builder.add_state(false);
+        let line = shebang_builder.add_state(true);
+        shebang_builder.add_transition(dfa::START, hash, b'#'..=b'#');
+        shebang_builder.add_transition(hash, line, b'!'..=b'!');
+        shebang_builder.add_transition(line, line, 0..=(b'\n' - 1));
+        shebang_builder.add_transition(line, line, (b'\n' + 1)..=u8::MAX);
+
+        let lexer = Lexer::new(vec![
+            (shebang_builder.build(), ShebangTokenKind::Shebang),
+            (dfa::keyword_automaton(*b"#"), ShebangTokenKind::Hash),
+            (ident_dfa(), ShebangTokenKind::Ident),
+        ])
+        .anchor_at_start(ShebangTokenKind::Shebang);
+
+        let byte_iter = "#!/bin/sh\n#!x".bytes();
+        let tokens: Vec<_> = lexer
+            .lex(byte_iter)
+            .map(|t| (t.kind(), t.text().map(<[u8]>::to_vec)))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (ShebangTokenKind::Shebang, Some(b"#!/bin/sh".to_vec())),
+                (ShebangTokenKind::Unknown, Some(b"\n".to_vec())),
+                (ShebangTokenKind::Hash, None),
+                (ShebangTokenKind::Unknown, Some(b"!".to_vec())),
+                (ShebangTokenKind::Ident, Some(b"x".to_vec())),
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_not_split_from_identifiers() {
+        let kinds = |source: &str| {
+            let lexer = Lexer::new(vec![
+                (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+                (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+                (ident_dfa(), TestLexerTokenKind::Ident),
+            ]);
+            let byte_iter = source.bytes();
+
+            lexer.lex(byte_iter).map(|t| t.kind()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(kinds("ifwhile"), vec![TestLexerTokenKind::Ident]);
+        assert_eq!(kinds("if_while"), vec![TestLexerTokenKind::Ident]);
+        assert_eq!(kinds("whileif"), vec![TestLexerTokenKind::Ident]);
+        assert_eq!(
+            kinds("if while"),
+            vec![
+                TestLexerTokenKind::If,
+                TestLexerTokenKind::Unknown,
+                TestLexerTokenKind::While
+            ]
+        );
+        assert_eq!(kinds("if"), vec![TestLexerTokenKind::If]);
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum DotTokenKind {
+        Dot,
+        Ident,
+        Unknown,
+    }
+
+    impl TokenKind for DotTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn coalesce_dots() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"."), DotTokenKind::Dot),
+            (ident_dfa(), DotTokenKind::Ident),
+        ]);
+        let byte_iter = "a...b.c".bytes();
+        let tokens: Vec<_> =
+            coalesce_kind(lexer.lex(byte_iter), DotTokenKind::Dot)
+                .map(|t| (t.kind(), t.text().unwrap().to_vec()))
+                .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (DotTokenKind::Ident, b"a".to_vec()),
+                (DotTokenKind::Dot, b"...".to_vec()),
+                (DotTokenKind::Ident, b"b".to_vec()),
+                (DotTokenKind::Dot, b".".to_vec()),
+                (DotTokenKind::Ident, b"c".to_vec()),
+            ]
+        );
+    }
+
+    fn assert_thread_safe<T: Send + Sync>() {}
+
+    #[test]
+    fn compiled_tables_are_thread_safe() {
+        assert_thread_safe::<dfa::Automaton<u8>>();
+        assert_thread_safe::<dfa::Automaton<char>>();
+        assert_thread_safe::<Lexer<u8, TestLexerTokenKind>>();
+        assert_thread_safe::<Token<u8, TestLexerTokenKind>>();
+    }
+
+    #[test]
+    fn lex_from_shared_lexer() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let sources = ["while(x)", "if y", "iffy(while)", "_a1 if(b"];
+        let lexer = Arc::new(Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]));
+        let lex = |lexer: &Lexer<u8, TestLexerTokenKind>, source: &str| {
+            let byte_iter = source.bytes();
+            lexer
+                .clone()
+                .lex(byte_iter)
+                .map(|t| t.into_parts())
+                .collect::<Vec<_>>()
+        };
+
+        let sequential: Vec<_> =
+            sources.iter().map(|source| lex(&lexer, source)).collect();
+        let concurrent: Vec<_> = sources
+            .into_iter()
+            .map(|source| {
+                let lexer = Arc::clone(&lexer);
+                thread::spawn(move || lex(&lexer, source))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert_eq!(concurrent, sequential);
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum EagerTokenKind {
+        Char,
+        Word,
+        Unknown,
+    }
+
+    impl TokenKind for EagerTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn eager_emit() {
+        let tokens = |lexer: Lexer<u8, EagerTokenKind>, source: &str| {
+            let byte_iter = source.bytes();
+            lexer
+                .lex(byte_iter)
+                .map(|t| (t.kind(), t.text().unwrap().to_vec()))
+                .collect::<Vec<_>>()
+        };
+        let lexer = || {
+            Lexer::new(vec![
+                (dfa::keyword_automaton(*b"aaa"), EagerTokenKind::Word),
+                (dfa::keyword_automaton(*b"a"), EagerTokenKind::Char),
+            ])
+        };
+
+        assert_eq!(
+            tokens(lexer(), "aaab"),
+            vec![
+                (EagerTokenKind::Word, b"aaa".to_vec()),
+                (EagerTokenKind::Unknown, b"b".to_vec()),
+            ]
+        );
+        assert_eq!(
+            tokens(
+                lexer().emit_mode(EagerTokenKind::Char, EmitMode::Eager),
+                "aaab"
+            ),
+            vec![
+                (EagerTokenKind::Char, b"a".to_vec()),
+                (EagerTokenKind::Char, b"a".to_vec()),
+                (EagerTokenKind::Char, b"a".to_vec()),
+                (EagerTokenKind::Unknown, b"b".to_vec()),
+            ]
+        );
+
+        let lexer = Lexer::new(vec![
+            (ident_dfa(), EagerTokenKind::Word),
+            (dfa::keyword_automaton(*b";"), EagerTokenKind::Char),
+        ])
+        .emit_mode(EagerTokenKind::Char, EmitMode::Eager);
+
+        assert_eq!(
+            tokens(lexer, "ab;c;"),
+            vec![
+                (EagerTokenKind::Word, b"ab".to_vec()),
+                (EagerTokenKind::Char, b";".to_vec()),
+                (EagerTokenKind::Word, b"c".to_vec()),
+                (EagerTokenKind::Char, b";".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn either_kinds() {
+        let keywords = vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ];
+        let operators =
+            vec![(dfa::keyword_automaton(*b"/"), SlashTokenKind::Div)];
+        let automata = keywords
+            .into_iter()
+            .map(|(automaton, kind)| (automaton, Either::Left(kind)))
+            .chain(
+                operators
+                    .into_iter()
+                    .map(|(automaton, kind)| (automaton, Either::Right(kind))),
+            )
+            .collect();
+        let byte_iter = "if/x".bytes();
+
+        let tokens: Vec<_> = Lexer::new(automata)
+            .lex(byte_iter)
+            .map(|t| t.into_parts())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (Either::Left(TestLexerTokenKind::If), None),
+                (Either::Right(SlashTokenKind::Div), None),
+                (Either::Left(TestLexerTokenKind::Ident), Some(vec![b'x'])),
+            ]
+        );
+        assert_eq!(
+            Either::<TestLexerTokenKind, SlashTokenKind>::unknown(),
+            Either::Left(TestLexerTokenKind::Unknown)
+        );
+    }
+
+    #[test]
+    fn expected_kinds_on_unknown() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (dfa::keyword_automaton(*b"("), TestLexerTokenKind::Paren),
+        ]);
+        let tokens = |lexer: Lexer<u8, TestLexerTokenKind>| {
+            let byte_iter = "ix(wh".bytes();
+            lexer
+                .lex(byte_iter)
+                .map(|t| (t.kind(), t.expected_kinds().to_vec()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            tokens(lexer.clone().report_expected_kinds(true)),
+            vec![
+                (TestLexerTokenKind::Unknown, vec![TestLexerTokenKind::If]),
+                (TestLexerTokenKind::Unknown, vec![]),
+                (TestLexerTokenKind::Paren, vec![]),
+                (TestLexerTokenKind::Unknown, vec![TestLexerTokenKind::While]),
+            ]
+        );
+        assert!(tokens(lexer)
+            .iter()
+            .all(|(_, expected)| expected.is_empty()));
+    }
+
+    #[test]
+    fn reversed_tokens() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+        let byte_iter = "if while xy".bytes();
+
+        let tokens: Vec<_> = lexer
+            .lex_reversed(byte_iter)
+            .into_iter()
+            .map(|t| (t.span().range(), t.into_parts()))
+            .collect();
+
+        // Reversing the order keeps each token's original span
+        assert_eq!(
+            tokens,
+            vec![
+                (9..11, (TestLexerTokenKind::Ident, Some(b"xy".to_vec()))),
+                (8..9, (TestLexerTokenKind::Unknown, None)),
+                (3..8, (TestLexerTokenKind::While, None)),
+                (2..3, (TestLexerTokenKind::Unknown, None)),
+                (0..2, (TestLexerTokenKind::If, None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn flushed_lines() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+        let lines = ["if wh", "ile x"];
+        let symbols = lines
+            .iter()
+            .flat_map(|line| line.bytes().map(Some).chain(Some(None)));
+
+        let tokens: Vec<_> = lexer
+            .clone()
+            .lex_with_flushes(symbols)
+            .map(|t| t.into_parts())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TestLexerTokenKind::If, None),
+                (TestLexerTokenKind::Unknown, None),
+                (TestLexerTokenKind::Ident, Some(b"wh".to_vec())),
+                (TestLexerTokenKind::Ident, Some(b"ile".to_vec())),
+                (TestLexerTokenKind::Unknown, None),
+                (TestLexerTokenKind::Ident, Some(b"x".to_vec())),
+            ]
+        );
+
+        let unflushed = lexer
+            .lex_with_flushes("if x".bytes().map(Some))
+            .map(|t| t.kind())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            unflushed,
+            vec![TestLexerTokenKind::If, TestLexerTokenKind::Unknown]
+        );
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum DirectiveTokenKind {
+        Directive,
+        At,
+        Space,
+        Unknown,
+    }
+
+    impl TokenKind for DirectiveTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn directive_switches_automata() {
+        let automata = || {
+            vec![
+                (
+                    dfa::keyword_automaton(*b"#on"),
+                    DirectiveTokenKind::Directive,
+                ),
+                (dfa::keyword_automaton(*b" "), DirectiveTokenKind::Space),
+            ]
+        };
+        let byte_iter = "@ #on @".bytes();
+        let mut driver = Lexer::new(automata()).drive(byte_iter);
+        let mut kinds = Vec::new();
+
+        while let Some(token) = driver.next() {
+            if token.kind() == DirectiveTokenKind::Directive {
+                let mut extended = automata();
+                extended.push((
+                    dfa::keyword_automaton(*b"@"),
+                    DirectiveTokenKind::At,
+                ));
+                driver.set_automata(extended);
+            }
+
+            kinds.push((token.kind(), token.span().range()));
+        }
+
+        assert_eq!(
+            kinds,
+            vec![
+                (DirectiveTokenKind::Unknown, 0..1),
+                (DirectiveTokenKind::Space, 1..2),
+                (DirectiveTokenKind::Directive, 2..5),
+                (DirectiveTokenKind::Space, 5..6),
+                (DirectiveTokenKind::At, 6..7),
+            ]
+        );
+    }
+
+    #[test]
+    fn many_automata() {
+        // The rules that matter come after seventy keywords
+        let mut automata: Vec<_> = (0..70)
+            .map(|idx| {
+                let keyword = alloc::format!("k{idx}");
+                (
+                    dfa::keyword_automaton(keyword.into_bytes()),
+                    TestLexerTokenKind::Paren,
+                )
+            })
+            .collect();
+        automata.push((dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If));
+        automata
+            .push((dfa::keyword_automaton(*b"("), TestLexerTokenKind::While));
+        automata.push((ident_dfa(), TestLexerTokenKind::Ident));
+        let lexer = Lexer::new(automata)
+            .emit_mode(TestLexerTokenKind::While, EmitMode::Eager);
+
+        let tokens: Vec<_> = lexer
+            .lex("k69 if((k7x k70".bytes())
+            .map(|t| t.into_parts())
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TestLexerTokenKind::Paren, None),
+                (TestLexerTokenKind::Unknown, None),
+                (TestLexerTokenKind::If, None),
+                (TestLexerTokenKind::While, None),
+                (TestLexerTokenKind::While, None),
+                (TestLexerTokenKind::Ident, Some(b"k7x".to_vec())),
+                (TestLexerTokenKind::Unknown, None),
+                (TestLexerTokenKind::Ident, Some(b"k70".to_vec())),
+            ]
+        );
+    }
+
+    #[test]
+    fn scored_ties() {
+        let kinds = |lexer: Lexer<u8, TestLexerTokenKind>| {
+            let byte_iter = "if while xyz".bytes();
+            lexer
+                .lex(byte_iter)
+                .map(|t| t.kind())
+                .filter(|kind| *kind != TestLexerTokenKind::Unknown)
+                .collect::<Vec<_>>()
+        };
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+
+        assert_eq!(
+            kinds(lexer.clone()),
+            vec![
+                TestLexerTokenKind::If,
+                TestLexerTokenKind::While,
+                TestLexerTokenKind::Ident,
+            ]
+        );
+
+        let lexer = lexer.score(TestLexerTokenKind::Ident, |text| {
+            if text == b"while" {
+                1
+            } else {
+                -1
+            }
+        });
+
+        assert_eq!(
+            kinds(lexer),
+            vec![
+                TestLexerTokenKind::If,
+                TestLexerTokenKind::Ident,
+                TestLexerTokenKind::Ident,
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbouring_kinds() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+        let byte_iter = "while(x".bytes();
+
+        let windows: Vec<_> = windows(lexer.lex(byte_iter))
+            .map(|(prev, t, next)| (prev, t.kind(), next))
+            .collect();
+
+        assert_eq!(
+            windows,
+            vec![
+                (
+                    None,
+                    TestLexerTokenKind::While,
+                    Some(TestLexerTokenKind::Unknown),
+                ),
+                (
+                    Some(TestLexerTokenKind::While),
+                    TestLexerTokenKind::Unknown,
+                    Some(TestLexerTokenKind::Ident),
+                ),
+                (
+                    Some(TestLexerTokenKind::Unknown),
+                    TestLexerTokenKind::Ident,
+                    None,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn lowercased_capture() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ])
+        .capture_transform(|text| text.make_ascii_lowercase());
+        let source = "if Foo\nBAR";
+
+        let tokens: Vec<_> = lexer
+            .lex_lines(source)
+            .filter(|(t, _)| t.kind() == TestLexerTokenKind::Ident)
+            .map(|(t, line)| (t.into_parts().1, line))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (Some(b"foo".to_vec()), "if Foo"),
+                (Some(b"bar".to_vec()), "BAR"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_with_lines() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+
+        let tokens: Vec<_> = lexer
+            .lex_lines("if x\nfoo")
+            .map(|(t, line)| (t.kind(), line))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TestLexerTokenKind::If, "if x"),
+                (TestLexerTokenKind::Unknown, "if x"),
+                (TestLexerTokenKind::Ident, "if x"),
+                (TestLexerTokenKind::Unknown, "if x"),
+                (TestLexerTokenKind::Ident, "foo"),
+            ]
+        );
+    }
+
+    #[test]
+    fn token_spans() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+        let source = "if  whilex ?while";
+
+        let tokens: Vec<_> = lexer
+            .clone()
+            .lex(source.bytes())
+            .map(|t| (t.kind(), t.span()))
+            .collect();
+
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|(kind, span)| (*kind, &source[span.range()]))
+                .collect::<Vec<_>>(),
+            vec![
+                (TestLexerTokenKind::If, "if"),
+                (TestLexerTokenKind::Unknown, " "),
+                (TestLexerTokenKind::Unknown, " "),
+                (TestLexerTokenKind::Ident, "whilex"),
+                (TestLexerTokenKind::Unknown, " "),
+                (TestLexerTokenKind::Unknown, "?"),
+                (TestLexerTokenKind::While, "while"),
+            ]
+        );
+        assert!(tokens
+            .windows(2)
+            .all(|pair| pair[0].1.end == pair[1].1.start));
+        assert_eq!(tokens.last().unwrap().1.end, source.len());
+
+        // Flush markers are not symbols, so they don't shift later spans
+        let symbols = "if".bytes().map(Some).chain(Some(None));
+        let symbols = symbols.chain(" x".bytes().map(Some)).chain(Some(None));
+        let spans: Vec<_> = lexer
+            .lex_with_flushes(symbols)
+            .map(|t| t.span().range())
+            .collect();
+
+        assert_eq!(spans, vec![0..2, 2..3, 3..4]);
+    }
+
+    #[test]
+    fn spanned_symbols() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+        // Line continuations are removed before lexing, leaving gaps
+        let source = "wh\\\nile x\\\ny";
+        let mut symbols = Vec::new();
+        let mut idx = 0;
+
+        while idx < source.len() {
+            if source[idx..].starts_with("\\\n") {
+                idx += 2;
+                continue;
+            }
+
+            let span = Span {
+                start: idx,
+                end: idx + 1,
+            };
+            symbols.push((source.as_bytes()[idx], span));
+            idx += 1;
+        }
+
+        let tokens: Vec<_> = lexer
+            .lex_spanned(symbols.into_iter())
+            .map(|t| (t.kind(), &source[t.span().range()]))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TestLexerTokenKind::While, "wh\\\nile"),
+                (TestLexerTokenKind::Unknown, " "),
+                (TestLexerTokenKind::Ident, "x\\\ny"),
+            ]
+        );
+    }
+
+    #[test]
+    fn token_traits() {
+        let rules = vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ];
+        let first: Vec<_> =
+            Lexer::new(rules.clone()).lex("if x".bytes()).collect();
+        let second: Vec<_> = Lexer::new(rules).lex("if x".bytes()).collect();
+
+        assert_eq!(first, second);
+        assert_eq!(first[2].clone(), first[2]);
+        assert_ne!(first[0], first[2]);
+        assert!(alloc::format!("{:?}", first[2]).contains("Ident"));
+    }
+
+    #[test]
+    fn constructed_tokens() {
+        let ident = Token::new(TestLexerTokenKind::Ident, b"x1".to_vec());
+        let paren = Token::new(TestLexerTokenKind::Paren, b"(".to_vec());
+
+        assert_eq!(ident.kind(), TestLexerTokenKind::Ident);
+        assert_eq!(ident.text(), Some(&b"x1"[..]));
+        assert_eq!(paren.text(), None);
+        assert_eq!(
+            ident.into_parts(),
+            (TestLexerTokenKind::Ident, Some(b"x1".to_vec()))
+        );
+        assert_eq!(paren.into_parts(), (TestLexerTokenKind::Paren, None));
+    }
+
+    #[test]
+    fn ambiguous_automata() {
+        let mut lowercase = dfa::AutomatonBuilder::<u8>::new();
+        let rest = lowercase.add_state(true);
+        lowercase.add_transition(dfa::START, rest, b'a'..=b'z');
+        lowercase.add_transition(rest, rest, b'a'..=b'z');
+        lowercase.add_transition(rest, rest, b'0'..=b'9');
+
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+            (lowercase.build(), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"("), TestLexerTokenKind::Paren),
+        ]);
+
+        let ambiguities = lexer.check_ambiguity(0..=u8::MAX);
+
+        assert_eq!(
+            ambiguities,
+            vec![
+                Ambiguity {
+                    first: (0, TestLexerTokenKind::If),
+                    second: (1, TestLexerTokenKind::Ident),
+                    overlap: Overlap::Contained,
+                    example: b"if".to_vec(),
+                },
+                Ambiguity {
+                    first: (0, TestLexerTokenKind::If),
+                    second: (2, TestLexerTokenKind::While),
+                    overlap: Overlap::Contained,
+                    example: b"if".to_vec(),
+                },
+                Ambiguity {
+                    first: (1, TestLexerTokenKind::Ident),
+                    second: (2, TestLexerTokenKind::While),
+                    overlap: Overlap::Shadowed,
+                    example: b"a".to_vec(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn classify_partial_input() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+        ]);
+
+        assert_eq!(lexer.classify_partial(b"whil"), PartialMatch::Incomplete);
+        assert_eq!(
+            lexer.classify_partial(b"while"),
+            PartialMatch::Complete(TestLexerTokenKind::While)
+        );
+        assert_eq!(lexer.classify_partial(b"wxy"), PartialMatch::Invalid);
+        assert_eq!(lexer.classify_partial(b"whilee"), PartialMatch::Invalid);
+        assert_eq!(lexer.classify_partial(b""), PartialMatch::Incomplete);
+    }
+
+    #[test]
+    fn diagnose_input() {
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"while"), TestLexerTokenKind::While),
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (dfa::keyword_automaton(*b"("), TestLexerTokenKind::Paren),
+        ]);
+
+        assert_eq!(
+            lexer.diagnose(b"whil"),
+            vec![
+                (TestLexerTokenKind::While, MatchResult::PartialThenNeedMore),
+                (TestLexerTokenKind::If, MatchResult::DiedAt(0)),
+                (TestLexerTokenKind::Paren, MatchResult::DiedAt(0)),
+            ]
+        );
+
+        let lexer = Lexer::new(vec![
+            (dfa::keyword_automaton(*b"if"), TestLexerTokenKind::If),
+            (ident_dfa(), TestLexerTokenKind::Ident),
+        ]);
+
+        assert_eq!(
+            lexer.diagnose(b"if("),
+            vec![
+                (TestLexerTokenKind::If, MatchResult::Prefix(2)),
+                (TestLexerTokenKind::Ident, MatchResult::Prefix(2)),
+            ]
+        );
+        assert_eq!(
+            lexer.diagnose(b"iffy"),
+            vec![
+                (TestLexerTokenKind::If, MatchResult::Prefix(2)),
+                (TestLexerTokenKind::Ident, MatchResult::Full),
+            ]
+        );
     }
 }
@@ -1,2 +1,25 @@
-pub(crate) mod dfa;
-mod lexer;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+pub use error::{Error, ErrorKind};
+
+pub mod brackets;
+pub mod dense;
+pub mod dfa;
+pub mod error;
+mod interval;
+#[cfg(feature = "std")]
+pub mod keyword;
+pub mod lexer;
+pub mod minimize;
+pub mod nfa;
+pub mod persist;
+pub mod position;
+pub mod product;
+#[cfg(feature = "std")]
+pub mod qc;
+pub mod recognizer;
+pub mod regex;
+#[cfg(feature = "testing")]
+pub mod testing;
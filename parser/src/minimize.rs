@@ -0,0 +1,284 @@
+use crate::dfa::{Automaton, State, Symbol, START};
+use crate::nfa::partition;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::ops::RangeInclusive;
+
+impl<Sym: Symbol> Automaton<Sym> {
+    /// Build the minimal automaton accepting the same language, with states
+    /// numbered in breadth-first order from START
+    pub fn minimize(&self) -> Automaton<Sym> {
+        let live = self.live_states();
+
+        if !live[START] {
+            return Automaton::from_states(vec![State::new(false)]);
+        }
+
+        let classes = partition(
+            self.states
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| live[*idx])
+                .flat_map(|(_, state)| state.edges())
+                .map(|(range, _)| range.clone()),
+        );
+
+        let target = |state: usize, class: &RangeInclusive<Sym>| {
+            self.states[state]
+                .transition(Some(*class.start()))
+                .filter(|to| live[*to])
+        };
+
+        let mut blocks: Vec<usize> = self
+            .states
+            .iter()
+            .map(|state| usize::from(state.accepting))
+            .collect();
+        let mut block_count = 0;
+
+        loop {
+            let mut signatures = BTreeMap::new();
+            let refined: Vec<usize> = (0..self.states.len())
+                .map(|idx| {
+                    let signature = (
+                        blocks[idx],
+                        classes
+                            .iter()
+                            .map(|class| {
+                                target(idx, class).map(|to| blocks[to])
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+                    let next = signatures.len();
+                    *signatures.entry(signature).or_insert(next)
+                })
+                .collect();
+
+            blocks = refined;
+
+            if signatures.len() == block_count {
+                break;
+            }
+
+            block_count = signatures.len();
+        }
+
+        let mut numbering = BTreeMap::from([(blocks[START], START)]);
+        let mut representatives = vec![START];
+        let mut queue = VecDeque::from([START]);
+        let mut states = Vec::new();
+
+        while let Some(representative) = queue.pop_front() {
+            let mut state = State::new(self.states[representative].accepting);
+
+            for class in &classes {
+                let Some(to) = target(representative, class) else {
+                    continue;
+                };
+
+                let next_idx =
+                    *numbering.entry(blocks[to]).or_insert_with(|| {
+                        representatives.push(to);
+                        queue.push_back(to);
+                        representatives.len() - 1
+                    });
+
+                state.add_transition(class.clone(), next_idx);
+            }
+
+            states.push(state);
+        }
+
+        let mut automaton = Automaton::from_states(states);
+        automaton.compact();
+        automaton
+    }
+
+    /// States reachable from START that can reach an accepting state
+    pub(crate) fn live_states(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.states.len()];
+        let mut stack = vec![START];
+
+        while let Some(idx) = stack.pop() {
+            if !reachable[idx] {
+                reachable[idx] = true;
+                stack.extend(self.states[idx].edges().map(|(_, to)| to));
+            }
+        }
+
+        let mut live: Vec<bool> =
+            self.states.iter().map(|state| state.accepting).collect();
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for (idx, state) in self.states.iter().enumerate() {
+                if !live[idx] && state.edges().any(|(_, to)| live[to]) {
+                    live[idx] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        live.iter().zip(reachable).map(|(l, r)| *l && r).collect()
+    }
+
+    /// Literal runs every accepted string contains, found by following the
+    /// chain of states from START that have a single live outgoing edge.
+    /// Edges over more than one symbol split the chain into separate runs.
+    pub fn required_literals(&self) -> Vec<Vec<Sym>> {
+        let live = self.live_states();
+        let mut visited = vec![false; self.states.len()];
+        let mut literals = Vec::new();
+        let mut literal = Vec::new();
+        let mut idx = START;
+
+        while live[idx] && !visited[idx] && !self.states[idx].accepting {
+            visited[idx] = true;
+            let mut edges =
+                self.states[idx].edges().filter(|(_, to)| live[*to]);
+
+            let (range, to) = match (edges.next(), edges.next()) {
+                (Some(edge), None) => edge,
+                _ => break,
+            };
+
+            if range.start() == range.end() {
+                literal.push(*range.start());
+            } else if !literal.is_empty() {
+                literals.push(core::mem::take(&mut literal));
+            }
+
+            idx = to;
+        }
+
+        if !literal.is_empty() {
+            literals.push(literal);
+        }
+
+        literals
+    }
+}
+
+impl<Sym: Symbol + fmt::Debug> Automaton<Sym> {
+    /// Render the minimized automaton; automata accepting the same language
+    /// produce identical output
+    pub fn canonical_form(&self) -> String {
+        let minimal = self.minimize();
+        let mut rendered = String::new();
+
+        for (idx, state) in minimal.states.iter().enumerate() {
+            let accepting = if state.accepting { " accepting" } else { "" };
+            writeln!(rendered, "{idx}{accepting}").unwrap();
+
+            let mut edges: Vec<_> = state.edges().collect();
+            edges.sort_by_key(|(range, _)| *range.start());
+
+            for (range, to) in edges {
+                if range.start() == range.end() {
+                    writeln!(rendered, "  {:?} -> {to}", range.start())
+                } else {
+                    writeln!(
+                        rendered,
+                        "  {:?}..={:?} -> {to}",
+                        range.start(),
+                        range.end()
+                    )
+                }
+                .unwrap();
+            }
+        }
+
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::{keyword_automaton, AutomatonBuilder};
+    use crate::product::product;
+
+    #[test]
+    fn canonical_keyword() {
+        let mut builder = AutomatonBuilder::new();
+        let dead = builder.add_state(false);
+        let end = builder.add_state(true);
+        let i = builder.add_state(false);
+        let unreachable = builder.add_state(true);
+        builder.add_transition(START, i, 'i'..='i');
+        builder.add_transition(START, dead, 'x'..='x');
+        builder.add_transition(i, end, 'f'..='f');
+        builder.add_transition(unreachable, end, 'q'..='q');
+        let hand_built = builder.build();
+
+        let keyword = keyword_automaton("if".chars());
+
+        assert_eq!(hand_built.canonical_form(), keyword.canonical_form());
+        assert_eq!(
+            keyword.canonical_form(),
+            "0\n  'i' -> 1\n1\n  'f' -> 2\n2 accepting\n"
+        );
+    }
+
+    #[test]
+    fn minimize_merges_equivalent_states() {
+        let mut builder = AutomatonBuilder::new();
+        let a = builder.add_state(true);
+        let b = builder.add_state(true);
+        builder.add_transition(START, a, b'a'..=b'a');
+        builder.add_transition(START, b, b'b'..=b'b');
+        builder.add_transition(a, a, b'0'..=b'4');
+        builder.add_transition(a, b, b'5'..=b'9');
+        builder.add_transition(b, b, b'0'..=b'9');
+        let minimal = builder.build().minimize();
+
+        assert_eq!(minimal.states.len(), 2);
+        assert!(minimal.accepts(*b"a0159"));
+        assert!(minimal.accepts(*b"b"));
+        assert!(!minimal.accepts(*b"c"));
+        assert_eq!(
+            minimal.canonical_form(),
+            "0\n  97..=98 -> 1\n1 accepting\n  48..=57 -> 1\n"
+        );
+    }
+
+    #[test]
+    fn minimize_empty_language() {
+        let mut builder = AutomatonBuilder::new();
+        let a = builder.add_state(false);
+        builder.add_transition(START, a, b'a'..=b'a');
+
+        assert_eq!(builder.build().canonical_form(), "0\n");
+    }
+
+    #[test]
+    fn required_literals() {
+        let hello = keyword_automaton("hello".chars());
+
+        assert_eq!(
+            hello.required_literals(),
+            vec!["hello".chars().collect::<Vec<_>>()]
+        );
+
+        let world = keyword_automaton("world".chars());
+        let union = product(&hello, &world, 'a'..='z', |a, b| a || b);
+
+        assert!(union.required_literals().is_empty());
+
+        let mut builder = AutomatonBuilder::new();
+        let x = builder.add_state(false);
+        let digit = builder.add_state(false);
+        let end = builder.add_state(true);
+        builder.add_transition(START, x, '0'..='0');
+        builder.add_transition(x, digit, 'x'..='x');
+        builder.add_transition(digit, digit, '0'..='9');
+        builder.add_transition(digit, end, 'h'..='h');
+
+        assert_eq!(builder.build().required_literals(), vec![vec!['0', 'x']]);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::dfa::{self, Automaton, State, Symbol};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeterminizationError {
+    TooManyStates,
+}
+
+impl fmt::Display for DeterminizationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TooManyStates => {
+                write!(f, "Determinization exceeded the state limit")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DeterminizationError {}
+
+struct NfaState<Sym: Copy + Ord> {
+    transitions: Vec<(RangeInclusive<Sym>, usize)>,
+    epsilon: Vec<usize>,
+    accepting: bool,
+}
+
+impl<Sym: Copy + Ord> NfaState<Sym> {
+    fn new(accepting: bool) -> Self {
+        Self {
+            transitions: Vec::new(),
+            epsilon: Vec::new(),
+            accepting,
+        }
+    }
+}
+
+/// Nondeterministic automaton with epsilon moves, used as an intermediate
+/// form for constructions that are awkward to express directly as a DFA
+pub struct Nfa<Sym: Copy + Ord> {
+    states: Vec<NfaState<Sym>>,
+}
+
+impl<Sym: Copy + Ord> Default for Nfa<Sym> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Sym: Copy + Ord> Nfa<Sym> {
+    pub fn new() -> Self {
+        Self {
+            states: vec![NfaState::new(false)],
+        }
+    }
+
+    pub fn from_dfa(automaton: &Automaton<Sym>) -> Self {
+        let mut nfa = Self { states: Vec::new() };
+        nfa.append_dfa(automaton);
+        nfa
+    }
+
+    /// Copy the states of `automaton` into this NFA, returning the index
+    /// its START state was given
+    pub fn append_dfa(&mut self, automaton: &Automaton<Sym>) -> usize {
+        let offset = self.states.len();
+
+        for state in &automaton.states {
+            let mut nfa_state = NfaState::new(state.accepting);
+            nfa_state.transitions = state
+                .edges()
+                .map(|(range, to)| (range.clone(), to + offset))
+                .collect();
+            self.states.push(nfa_state);
+        }
+
+        offset
+    }
+
+    pub fn add_state(&mut self, accepting: bool) -> usize {
+        let idx = self.states.len();
+        self.states.push(NfaState::new(accepting));
+        idx
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_accepting(&self, state: usize) -> bool {
+        self.states[state].accepting
+    }
+
+    pub fn set_accepting(&mut self, state: usize, accepting: bool) {
+        self.states[state].accepting = accepting;
+    }
+
+    pub fn add_transition(
+        &mut self,
+        from: usize,
+        to: usize,
+        symbols: RangeInclusive<Sym>,
+    ) {
+        self.check_states(from, to);
+        self.states[from].transitions.push((symbols, to));
+    }
+
+    pub fn add_epsilon(&mut self, from: usize, to: usize) {
+        self.check_states(from, to);
+        self.states[from].epsilon.push(to);
+    }
+
+    fn check_states(&self, from: usize, to: usize) {
+        if from >= self.states.len() {
+            panic!("Transition 'from' argument exceeds state count");
+        }
+
+        if to >= self.states.len() {
+            panic!("Transition 'to' argument exceeds state count");
+        }
+    }
+
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut closure = BTreeSet::new();
+        let mut stack: Vec<usize> = states.into_iter().collect();
+
+        while let Some(idx) = stack.pop() {
+            if closure.insert(idx) {
+                stack.extend(self.states[idx].epsilon.iter().copied());
+            }
+        }
+
+        closure.into_iter().collect()
+    }
+}
+
+impl<Sym: Symbol> Nfa<Sym> {
+    /// Determinize via subset construction
+    pub fn to_dfa(&self) -> Automaton<Sym> {
+        self.to_dfa_limited(usize::MAX)
+            .expect("Unlimited determinization cannot exceed its limit")
+    }
+
+    /// Determinize via subset construction, failing if the resulting
+    /// automaton would need more than `max_states` states
+    pub fn to_dfa_limited(
+        &self,
+        max_states: usize,
+    ) -> Result<Automaton<Sym>, DeterminizationError> {
+        let mut subsets = vec![self.closure([dfa::START])];
+        let mut indices = BTreeMap::from([(subsets[0].clone(), dfa::START)]);
+        let mut states = Vec::new();
+
+        while states.len() < subsets.len() {
+            let subset = subsets[states.len()].clone();
+            let mut state = State::<Sym>::new(
+                subset.iter().any(|idx| self.states[*idx].accepting),
+            );
+
+            let outgoing = subset
+                .iter()
+                .flat_map(|idx| self.states[*idx].transitions.iter());
+
+            for range in partition(outgoing.clone().map(|(r, _)| r.clone())) {
+                let targets = self.closure(
+                    outgoing
+                        .clone()
+                        .filter(|(r, _)| r.contains(range.start()))
+                        .map(|(_, to)| *to),
+                );
+
+                let next_idx = match indices.get(&targets) {
+                    Some(idx) => *idx,
+                    None if subsets.len() >= max_states => {
+                        return Err(DeterminizationError::TooManyStates);
+                    }
+                    None => {
+                        subsets.push(targets.clone());
+                        indices.insert(targets, subsets.len() - 1);
+                        subsets.len() - 1
+                    }
+                };
+
+                state.add_transition(range, next_idx);
+            }
+
+            states.push(state);
+        }
+
+        let mut automaton = Automaton::from_states(states);
+        automaton.compact();
+        Ok(automaton)
+    }
+}
+
+/// Split possibly-overlapping ranges into disjoint ranges covering the same
+/// symbols, such that each result lies entirely inside or outside each input
+pub(crate) fn partition<Sym: Symbol>(
+    ranges: impl Iterator<Item = RangeInclusive<Sym>>,
+) -> Vec<RangeInclusive<Sym>> {
+    let ranges: Vec<_> = ranges.filter(|r| !r.is_empty()).collect();
+    let mut cuts: Vec<Sym> = ranges
+        .iter()
+        .flat_map(|r| Some(*r.start()).into_iter().chain(r.end().successor()))
+        .collect();
+    cuts.sort();
+    cuts.dedup();
+
+    let mut partition = Vec::new();
+
+    for (i, start) in cuts.iter().enumerate() {
+        let end = match cuts.get(i + 1) {
+            Some(next) => next.predecessor().unwrap(),
+            None => match ranges.iter().map(|r| *r.end()).max() {
+                Some(end) if end >= *start => end,
+                _ => continue,
+            },
+        };
+
+        if ranges.iter().any(|r| r.contains(start)) {
+            partition.push(*start..=end);
+        }
+    }
+
+    partition
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_overlapping() {
+        let parts =
+            partition([b'a'..=b'm', b'f'..=b'z', b'0'..=b'0'].into_iter());
+
+        assert_eq!(
+            parts,
+            vec![b'0'..=b'0', b'a'..=b'e', b'f'..=b'm', b'n'..=b'z']
+        );
+
+        let parts = partition([0..=u8::MAX, b'.'..=b'.'].into_iter());
+
+        assert_eq!(parts, vec![0..=b'-', b'.'..=b'.', b'/'..=u8::MAX]);
+    }
+
+    #[test]
+    fn determinize_alternation() {
+        let mut nfa = Nfa::<u8>::new();
+        let a = nfa.add_state(false);
+        let a_end = nfa.add_state(true);
+        let b = nfa.add_state(false);
+        let b_end = nfa.add_state(true);
+        nfa.add_epsilon(dfa::START, a);
+        nfa.add_epsilon(dfa::START, b);
+        nfa.add_transition(a, a_end, b'a'..=b'z');
+        nfa.add_transition(b, b_end, b'x'..=b'x');
+        nfa.add_transition(b_end, b_end, b'0'..=b'9');
+
+        let automaton = nfa.to_dfa();
+
+        assert!(automaton.accepts(*b"q"));
+        assert!(automaton.accepts(*b"x"));
+        assert!(automaton.accepts(*b"x12"));
+        assert!(!automaton.accepts(*b"q12"));
+        assert!(!automaton.accepts(*b""));
+    }
+
+    // (a|b)*a(a|b){n}: the DFA must remember the last n + 1 symbols
+    fn blowup_nfa(n: usize) -> Nfa<u8> {
+        let mut nfa = Nfa::new();
+        nfa.add_transition(dfa::START, dfa::START, b'a'..=b'b');
+        let mut current = nfa.add_state(false);
+        nfa.add_transition(dfa::START, current, b'a'..=b'a');
+
+        for _ in 0..n {
+            let next = nfa.add_state(false);
+            nfa.add_transition(current, next, b'a'..=b'b');
+            current = next;
+        }
+
+        nfa.set_accepting(current, true);
+        nfa
+    }
+
+    #[test]
+    fn determinize_state_limit() {
+        let nfa = blowup_nfa(12);
+
+        assert_eq!(
+            nfa.to_dfa_limited(1000).err(),
+            Some(DeterminizationError::TooManyStates)
+        );
+
+        let automaton = blowup_nfa(3).to_dfa_limited(1000).unwrap();
+
+        assert!(automaton.accepts(*b"babbb"));
+        assert!(!automaton.accepts(*b"bbbbb"));
+    }
+}
@@ -0,0 +1,192 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+const MAGIC: [u8; 4] = *b"QCTF";
+const HEADER_LEN: usize = 4 + 2 + 2 + 2 + 8 + 8;
+
+/// Format version; readers accept any payload with the same major version
+/// and a minor version no newer than their own
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Version {
+    pub major: u16,
+    pub minor: u16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PersistError {
+    WrongMagic,
+    WrongFormat { expected: u16, found: u16 },
+    IncompatibleVersion { supported: Version, found: Version },
+    CorruptPayload,
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongMagic => write!(f, "Not a qctools file"),
+            Self::WrongFormat { expected, found } => {
+                write!(f, "Expected format {expected}, found format {found}")
+            }
+            Self::IncompatibleVersion { supported, found } => write!(
+                f,
+                "Version {}.{} is not readable by version {}.{}",
+                found.major, found.minor, supported.major, supported.minor
+            ),
+            Self::CorruptPayload => {
+                write!(f, "Payload is truncated or corrupt")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PersistError {}
+
+/// Prefix `payload` with a header recording its format, version and a hash
+/// covering both the header fields and the payload
+pub fn write_framed(format: u16, version: Version, payload: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(HEADER_LEN + payload.len());
+    framed.extend_from_slice(&MAGIC);
+    framed.extend_from_slice(&format.to_le_bytes());
+    framed.extend_from_slice(&version.major.to_le_bytes());
+    framed.extend_from_slice(&version.minor.to_le_bytes());
+    framed.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    let hash = hash(&[&framed[MAGIC.len()..], payload]);
+    framed.extend_from_slice(&hash.to_le_bytes());
+    framed.extend_from_slice(payload);
+    framed
+}
+
+/// Validate the header written by `write_framed` and return the payload
+pub fn read_framed(
+    framed: &[u8],
+    format: u16,
+    supported: Version,
+) -> Result<&[u8], PersistError> {
+    if framed.get(..MAGIC.len()) != Some(&MAGIC[..]) {
+        return Err(PersistError::WrongMagic);
+    }
+
+    let Some((header, payload)) = framed.split_at_checked(HEADER_LEN) else {
+        return Err(PersistError::CorruptPayload);
+    };
+
+    let u16_at =
+        |idx: usize| u16::from_le_bytes([header[idx], header[idx + 1]]);
+    let u64_at = |idx: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&header[idx..idx + 8]);
+        u64::from_le_bytes(bytes)
+    };
+
+    let found_format = u16_at(4);
+
+    if found_format != format {
+        return Err(PersistError::WrongFormat {
+            expected: format,
+            found: found_format,
+        });
+    }
+
+    let found = Version {
+        major: u16_at(6),
+        minor: u16_at(8),
+    };
+
+    if found.major != supported.major || found.minor > supported.minor {
+        return Err(PersistError::IncompatibleVersion { supported, found });
+    }
+
+    if u64_at(10) != payload.len() as u64
+        || u64_at(18) != hash(&[&header[MAGIC.len()..18], payload])
+    {
+        return Err(PersistError::CorruptPayload);
+    }
+
+    Ok(payload)
+}
+
+/// 64-bit FNV-1a over the concatenation of `parts`
+fn hash(parts: &[&[u8]]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.iter())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT: u16 = 7;
+    const VERSION: Version = Version { major: 1, minor: 2 };
+
+    #[test]
+    fn round_trip() {
+        let framed = write_framed(FORMAT, VERSION, b"payload");
+
+        assert_eq!(read_framed(&framed, FORMAT, VERSION), Ok(&b"payload"[..]));
+        assert_eq!(
+            read_framed(&framed, FORMAT, Version { major: 1, minor: 5 }),
+            Ok(&b"payload"[..])
+        );
+    }
+
+    #[test]
+    fn header_mismatches() {
+        let framed = write_framed(FORMAT, VERSION, b"payload");
+
+        assert_eq!(
+            read_framed(b"nope, not a file", FORMAT, VERSION),
+            Err(PersistError::WrongMagic)
+        );
+        assert_eq!(
+            read_framed(&framed, 8, VERSION),
+            Err(PersistError::WrongFormat {
+                expected: 8,
+                found: FORMAT
+            })
+        );
+        assert_eq!(
+            read_framed(&framed, FORMAT, Version { major: 1, minor: 1 }),
+            Err(PersistError::IncompatibleVersion {
+                supported: Version { major: 1, minor: 1 },
+                found: VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn old_header_versions() {
+        let old_minor =
+            write_framed(FORMAT, Version { major: 1, minor: 0 }, b"x");
+        let old_major =
+            write_framed(FORMAT, Version { major: 0, minor: 9 }, b"x");
+
+        assert_eq!(read_framed(&old_minor, FORMAT, VERSION), Ok(&b"x"[..]));
+        assert!(matches!(
+            read_framed(&old_major, FORMAT, VERSION),
+            Err(PersistError::IncompatibleVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        let framed = write_framed(FORMAT, VERSION, b"some longer payload");
+
+        for len in 0..framed.len() {
+            assert!(read_framed(&framed[..len], FORMAT, VERSION).is_err());
+        }
+
+        for idx in 0..framed.len() {
+            for bit in 0..8 {
+                let mut flipped = framed.clone();
+                flipped[idx] ^= 1 << bit;
+
+                assert!(read_framed(&flipped, FORMAT, VERSION).is_err());
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Half-open range of symbol offsets in the source
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Smallest span covering both spans
+    pub fn union(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    pub fn range(self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Newline offsets recorded while symbols are fed to a lexer, used to map
+/// symbol offsets back to 1-based line and column numbers
+pub struct LineMap {
+    line_starts: Vec<usize>,
+    /// `(offset, column)` for symbols whose column doesn't follow from the
+    /// previous symbol's, e.g. after a tab
+    column_jumps: Vec<(usize, u32)>,
+}
+
+impl LineMap {
+    pub fn position(&self, offset: usize) -> (u32, u32) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+
+        let line_start = self.line_starts[line];
+        let jump = self
+            .column_jumps
+            .partition_point(|(jump_offset, _)| *jump_offset <= offset)
+            .checked_sub(1)
+            .map(|idx| self.column_jumps[idx])
+            .filter(|(jump_offset, _)| *jump_offset > line_start);
+
+        let column = match jump {
+            Some((jump_offset, column)) => {
+                column + (offset - jump_offset) as u32
+            }
+            None => (offset - line_start) as u32 + 1,
+        };
+
+        (line as u32 + 1, column)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Offset range of 1-based `line`, excluding its terminating newline
+    pub fn line_range(&self, line: u32, len: usize) -> Option<Range<usize>> {
+        let idx = (line as usize).checked_sub(1)?;
+        let start = *self.line_starts.get(idx)?;
+        let end = self
+            .line_starts
+            .get(idx + 1)
+            .map_or(len, |next_start| next_start - 1);
+
+        Some(start..end)
+    }
+}
+
+pub struct LineMapBuilder<Sym: Copy + Eq> {
+    newline: Sym,
+    advance: fn(u32, Sym) -> u32,
+    offset: usize,
+    column: u32,
+    line_starts: Vec<usize>,
+    column_jumps: Vec<(usize, u32)>,
+}
+
+impl<Sym: Copy + Eq> LineMapBuilder<Sym> {
+    pub fn new(newline: Sym) -> Self {
+        Self {
+            newline,
+            advance: |column, _| column + 1,
+            offset: 0,
+            column: 1,
+            line_starts: vec![0],
+            column_jumps: Vec::new(),
+        }
+    }
+
+    /// Compute the column following a symbol from its own column, e.g. to
+    /// round tabs up to the next tab stop. Columns are 1-based.
+    pub fn with_advance(mut self, advance: fn(u32, Sym) -> u32) -> Self {
+        self.advance = advance;
+        self
+    }
+
+    /// Pass `symbols` through unchanged, recording the offsets of newlines
+    pub fn track<'a>(
+        &'a mut self,
+        symbols: impl Iterator<Item = Sym> + 'a,
+    ) -> impl Iterator<Item = Sym> + 'a {
+        symbols.inspect(move |symbol| {
+            self.offset += 1;
+
+            if *symbol == self.newline {
+                self.line_starts.push(self.offset);
+                self.column = 1;
+            } else {
+                let column = (self.advance)(self.column, *symbol);
+
+                if column != self.column + 1 {
+                    self.column_jumps.push((self.offset, column));
+                }
+
+                self.column = column;
+            }
+        })
+    }
+
+    pub fn finish(self) -> LineMap {
+        LineMap {
+            line_starts: self.line_starts,
+            column_jumps: self.column_jumps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa;
+    use crate::lexer::{Lexer, TokenKind};
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum TestTokenKind {
+        Word,
+        Unknown,
+    }
+
+    impl TokenKind for TestTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn line_map_positions() {
+        let lexer = Lexer::new(vec![(
+            dfa::keyword_automaton(*b"ab"),
+            TestTokenKind::Word,
+        )]);
+        let mut builder = LineMapBuilder::new(b'\n');
+        let source = "ab\n\nab ab\nx";
+        let byte_iter = source.bytes();
+        let token_count = lexer.lex(builder.track(byte_iter)).count();
+        let line_map = builder.finish();
+
+        assert!(token_count > 0);
+        assert_eq!(line_map.line_count(), 4);
+        assert_eq!(line_map.position(0), (1, 1));
+        assert_eq!(line_map.position(2), (1, 3));
+        assert_eq!(line_map.position(3), (2, 1));
+        assert_eq!(line_map.position(4), (3, 1));
+        assert_eq!(line_map.position(7), (3, 4));
+        assert_eq!(line_map.position(10), (4, 1));
+        assert_eq!(line_map.position(11), (4, 2));
+    }
+
+    #[test]
+    fn tab_stops() {
+        let mut builder =
+            LineMapBuilder::new(b'\n').with_advance(|column, symbol| {
+                if symbol == b'\t' {
+                    (column - 1) / 4 * 4 + 5
+                } else {
+                    column + 1
+                }
+            });
+        let source = "ab\tc\td\n\tx";
+        let byte_iter = source.bytes();
+        builder.track(byte_iter).for_each(drop);
+        let line_map = builder.finish();
+
+        assert_eq!(line_map.position(1), (1, 2));
+        assert_eq!(line_map.position(2), (1, 3));
+        assert_eq!(line_map.position(3), (1, 5));
+        assert_eq!(line_map.position(4), (1, 6));
+        assert_eq!(line_map.position(5), (1, 9));
+        assert_eq!(line_map.position(7), (2, 1));
+        assert_eq!(line_map.position(8), (2, 5));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::dfa::{self, Automaton, State, Symbol};
+use crate::nfa::partition;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+type PairState = (Option<usize>, Option<usize>);
+
+/// Run `first` and `second` in lockstep over `alphabet`. A state of the
+/// result accepts when `accepting` holds for the acceptance of the
+/// component states; a component with no transition is treated as being in
+/// a non-accepting sink.
+pub fn product<Sym: Symbol>(
+    first: &Automaton<Sym>,
+    second: &Automaton<Sym>,
+    alphabet: RangeInclusive<Sym>,
+    accepting: impl Fn(bool, bool) -> bool,
+) -> Automaton<Sym> {
+    let is_accepting = |automaton: &Automaton<Sym>, state: Option<usize>| {
+        state.is_some_and(|idx| automaton.states[idx].accepting)
+    };
+
+    let mut pairs: Vec<PairState> = vec![(Some(dfa::START), Some(dfa::START))];
+    let mut indices = BTreeMap::from([(pairs[0], dfa::START)]);
+    let mut states = Vec::new();
+
+    while states.len() < pairs.len() {
+        let (a, b) = pairs[states.len()];
+        let mut state = State::new(accepting(
+            is_accepting(first, a),
+            is_accepting(second, b),
+        ));
+
+        let ranges = [(first, a), (second, b)]
+            .into_iter()
+            .filter_map(|(automaton, state)| state.map(|s| (automaton, s)))
+            .flat_map(|(automaton, s)| automaton.states[s].edges())
+            .map(|(range, _)| range.clone())
+            .chain([alphabet.clone()]);
+
+        for range in partition(ranges) {
+            if !alphabet.contains(range.start()) {
+                continue;
+            }
+
+            let symbol = Some(*range.start());
+            let next = (
+                a.and_then(|s| first.states[s].transition(symbol)),
+                b.and_then(|s| second.states[s].transition(symbol)),
+            );
+
+            let next_idx = *indices.entry(next).or_insert_with(|| {
+                pairs.push(next);
+                pairs.len() - 1
+            });
+
+            state.add_transition(range, next_idx);
+        }
+
+        states.push(state);
+    }
+
+    let mut automaton = Automaton::from_states(states);
+    automaton.compact();
+    automaton
+}
+
+impl<Sym: Symbol> Automaton<Sym> {
+    /// Whether every string accepted by `self` is accepted by `other`,
+    /// considering only strings over `alphabet`
+    pub fn is_subset_of(
+        &self,
+        other: &Automaton<Sym>,
+        alphabet: RangeInclusive<Sym>,
+    ) -> bool {
+        product(self, other, alphabet, |a, b| a && !b).is_empty()
+    }
+}
+
+/// Example strings of at most `max_len` symbols accepted by `first` but not
+/// `second`, and vice versa. Each transition range contributes only its
+/// first symbol, so the examples are representative rather than exhaustive.
+pub fn language_diff<Sym: Symbol>(
+    first: &Automaton<Sym>,
+    second: &Automaton<Sym>,
+    alphabet: RangeInclusive<Sym>,
+    max_len: usize,
+) -> (Vec<Vec<Sym>>, Vec<Vec<Sym>>) {
+    let only_first = product(first, second, alphabet.clone(), |a, b| a && !b);
+    let only_second = product(first, second, alphabet, |a, b| !a && b);
+
+    (
+        examples(&only_first, max_len),
+        examples(&only_second, max_len),
+    )
+}
+
+/// Shortest string over `alphabet` accepted by exactly one of `first` and
+/// `second`, or `None` if they accept the same language
+pub fn distinguishing_string<Sym: Symbol>(
+    first: &Automaton<Sym>,
+    second: &Automaton<Sym>,
+    alphabet: RangeInclusive<Sym>,
+) -> Option<Vec<Sym>> {
+    shortest_accepted(&product(first, second, alphabet, |a, b| a != b))
+}
+
+/// Shortest string accepted by `automaton`, if any
+pub(crate) fn shortest_accepted<Sym: Symbol>(
+    automaton: &Automaton<Sym>,
+) -> Option<Vec<Sym>> {
+    let mut parents: Vec<Option<(usize, Sym)>> =
+        vec![None; automaton.states.len()];
+    let mut visited = vec![false; automaton.states.len()];
+    let mut queue = VecDeque::from([dfa::START]);
+    visited[dfa::START] = true;
+
+    while let Some(idx) = queue.pop_front() {
+        if automaton.states[idx].accepting {
+            let mut witness = Vec::new();
+            let mut current = idx;
+
+            while let Some((parent, symbol)) = parents[current] {
+                witness.push(symbol);
+                current = parent;
+            }
+
+            witness.reverse();
+            return Some(witness);
+        }
+
+        for (range, to) in automaton.states[idx].edges() {
+            if !visited[to] {
+                visited[to] = true;
+                parents[to] = Some((idx, *range.start()));
+                queue.push_back(to);
+            }
+        }
+    }
+
+    None
+}
+
+fn examples<Sym: Symbol>(
+    automaton: &Automaton<Sym>,
+    max_len: usize,
+) -> Vec<Vec<Sym>> {
+    let live = automaton.live_states();
+    let mut examples = Vec::new();
+    let mut frontier = vec![(dfa::START, Vec::new())];
+
+    for len in 0..=max_len {
+        let mut next_frontier = Vec::new();
+
+        for (idx, prefix) in frontier {
+            let state = &automaton.states[idx];
+
+            if state.accepting {
+                examples.push(prefix.clone());
+            }
+
+            if len == max_len {
+                continue;
+            }
+
+            for (range, to) in state.edges().filter(|(_, to)| live[*to]) {
+                let mut extended = prefix.clone();
+                extended.push(*range.start());
+                next_frontier.push((to, extended));
+            }
+        }
+
+        frontier = next_frontier;
+    }
+
+    examples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::AutomatonBuilder;
+
+    fn positive_integer() -> Automaton<u8> {
+        let mut builder = AutomatonBuilder::new();
+        let digits = builder.add_state(true);
+        builder.add_transition(dfa::START, digits, b'0'..=b'9');
+        builder.add_transition(digits, digits, b'0'..=b'9');
+        builder.build()
+    }
+
+    fn signed_integer() -> Automaton<u8> {
+        let mut builder = AutomatonBuilder::new();
+        let sign = builder.add_state(false);
+        let digits = builder.add_state(true);
+        builder.add_transition(dfa::START, sign, b'+'..=b'+');
+        builder.add_transition(dfa::START, sign, b'-'..=b'-');
+        builder.add_transition(dfa::START, digits, b'0'..=b'9');
+        builder.add_transition(sign, digits, b'0'..=b'9');
+        builder.add_transition(digits, digits, b'0'..=b'9');
+        builder.build()
+    }
+
+    #[test]
+    fn intersection() {
+        let both = product(
+            &signed_integer(),
+            &dfa::keyword_automaton(*b"-12"),
+            0..=u8::MAX,
+            |a, b| a && b,
+        );
+
+        assert!(both.accepts(*b"-12"));
+        assert!(!both.accepts(*b"12"));
+        assert!(!both.accepts(*b"-1"));
+    }
+
+    #[test]
+    fn subset() {
+        assert!(positive_integer().is_subset_of(&signed_integer(), 0..=u8::MAX));
+        assert!(
+            !signed_integer().is_subset_of(&positive_integer(), 0..=u8::MAX)
+        );
+        assert!(
+            positive_integer().is_subset_of(&positive_integer(), 0..=u8::MAX)
+        );
+    }
+
+    #[test]
+    fn language_differences() {
+        let (only_cat, only_car) = language_diff(
+            &dfa::keyword_automaton(*b"cat"),
+            &dfa::keyword_automaton(*b"car"),
+            0..=u8::MAX,
+            4,
+        );
+
+        assert_eq!(only_cat, vec![b"cat".to_vec()]);
+        assert_eq!(only_car, vec![b"car".to_vec()]);
+
+        let (only_signed, only_positive) = language_diff(
+            &signed_integer(),
+            &positive_integer(),
+            0..=u8::MAX,
+            2,
+        );
+
+        assert_eq!(only_signed, vec![b"+0".to_vec(), b"-0".to_vec()]);
+        assert!(only_positive.is_empty());
+        assert_eq!(
+            language_diff(&signed_integer(), &signed_integer(), 0..=u8::MAX, 3),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn distinguishing_strings() {
+        let witness = distinguishing_string(
+            &dfa::keyword_automaton(*b"ab"),
+            &dfa::keyword_automaton(*b"ac"),
+            0..=u8::MAX,
+        );
+
+        assert!(matches!(witness.as_deref(), Some(b"ab" | b"ac")));
+        assert_eq!(
+            distinguishing_string(
+                &signed_integer(),
+                &positive_integer(),
+                0..=u8::MAX
+            ),
+            Some(b"+0".to_vec())
+        );
+        assert_eq!(
+            distinguishing_string(
+                &positive_integer(),
+                &positive_integer().minimize(),
+                0..=u8::MAX
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,9 @@
+pub mod string_pool;
+pub mod strings;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Dialect {
+    #[default]
+    Vanilla,
+    Fte,
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+/// Interned string table in the progs layout: NUL-terminated strings
+/// addressed by byte offset, with offset 0 holding the empty string
+pub struct StringPool {
+    data: Vec<u8>,
+    offsets: HashMap<Vec<u8>, usize>,
+    uses: Vec<(usize, Range<usize>)>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StringPoolStats {
+    pub literal_count: usize,
+    pub unique_count: usize,
+    pub total_bytes: usize,
+    pub saved_bytes: usize,
+}
+
+impl Default for StringPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringPool {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0],
+            offsets: HashMap::from([(vec![], 0)]),
+            uses: Vec::new(),
+        }
+    }
+
+    /// Intern an unescaped literal found at `span`, returning its offset.
+    /// A literal containing a NUL is cut short there, since that is where
+    /// the engine stops reading it.
+    pub fn add(&mut self, literal: &[u8], span: Range<usize>) -> usize {
+        let literal = match literal.iter().position(|b| *b == 0) {
+            Some(len) => &literal[..len],
+            None => literal,
+        };
+        let offset = match self.offsets.get(literal) {
+            Some(offset) => *offset,
+            None => {
+                let offset = self.data.len();
+                self.data.extend_from_slice(literal);
+                self.data.push(0);
+                self.offsets.insert(literal.to_vec(), offset);
+                offset
+            }
+        };
+
+        self.uses.push((offset, span));
+        offset
+    }
+
+    pub fn get(&self, offset: usize) -> Option<&[u8]> {
+        let tail = self.data.get(offset..)?;
+        let len = tail.iter().position(|b| *b == 0)?;
+        Some(&tail[..len])
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn stats(&self) -> StringPoolStats {
+        let total_bytes = self
+            .uses
+            .iter()
+            .map(|(offset, _)| self.get(*offset).map_or(0, <[u8]>::len) + 1)
+            .sum::<usize>();
+        let unique_bytes = self
+            .uses
+            .iter()
+            .map(|(offset, _)| *offset)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|offset| self.get(offset).map_or(0, <[u8]>::len) + 1)
+            .sum::<usize>();
+
+        StringPoolStats {
+            literal_count: self.uses.len(),
+            unique_count: self.offsets.len() - 1,
+            total_bytes,
+            saved_bytes: total_bytes - unique_bytes,
+        }
+    }
+
+    /// Literals used more than `threshold` times, with the spans of every
+    /// use, ordered by first use
+    pub fn repeated(
+        &self,
+        threshold: usize,
+    ) -> Vec<(&[u8], Vec<Range<usize>>)> {
+        let mut spans: Vec<(usize, Vec<Range<usize>>)> = Vec::new();
+
+        for (offset, span) in &self.uses {
+            match spans.iter_mut().find(|(o, _)| o == offset) {
+                Some((_, list)) => list.push(span.clone()),
+                None => spans.push((*offset, vec![span.clone()])),
+            }
+        }
+
+        spans
+            .into_iter()
+            .filter(|(_, list)| list.len() > threshold)
+            .map(|(offset, list)| (self.get(offset).unwrap(), list))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedup_and_stats() {
+        let mut pool = StringPool::new();
+        let hello = pool.add(b"hello", 0..7);
+        let world = pool.add(b"world", 10..17);
+        let hello_again = pool.add(b"hello", 20..27);
+        pool.add(b"hello", 30..37);
+
+        assert_eq!(hello, 1);
+        assert_eq!(world, 7);
+        assert_eq!(hello_again, hello);
+        assert_eq!(pool.get(world), Some(&b"world"[..]));
+        assert_eq!(pool.get(0), Some(&b""[..]));
+        assert_eq!(pool.data(), b"\0hello\0world\0");
+        assert_eq!(
+            pool.stats(),
+            StringPoolStats {
+                literal_count: 4,
+                unique_count: 2,
+                total_bytes: 24,
+                saved_bytes: 12,
+            }
+        );
+    }
+
+    #[test]
+    fn repeated_literals() {
+        let mut pool = StringPool::new();
+        pool.add(b"a", 0..3);
+        pool.add(b"b", 5..8);
+        pool.add(b"a", 10..13);
+        pool.add(b"a", 20..23);
+        pool.add(b"b", 30..33);
+
+        assert_eq!(
+            pool.repeated(2),
+            vec![(&b"a"[..], vec![0..3, 10..13, 20..23])]
+        );
+        assert_eq!(pool.repeated(1).len(), 2);
+    }
+
+    #[test]
+    fn embedded_nul() {
+        let mut pool = StringPool::new();
+        let cut = pool.add(b"ab\0cd", 0..8);
+        let whole = pool.add(b"ab", 10..14);
+
+        assert_eq!(cut, whole);
+        assert_eq!(pool.get(cut), Some(&b"ab"[..]));
+        assert_eq!(pool.add(b"\0", 20..25), 0);
+        assert_eq!(pool.data(), b"\0ab\0");
+        assert_eq!(pool.repeated(1), vec![(&b"ab"[..], vec![0..8, 10..14])]);
+        assert_eq!(
+            pool.stats(),
+            StringPoolStats {
+                literal_count: 3,
+                unique_count: 1,
+                total_bytes: 7,
+                saved_bytes: 3,
+            }
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use super::Dialect;
+
+/// Toggles the high bit ("gold text") on subsequent characters
+const GOLD_TOGGLE: u8 = b's';
+const HIGH_BIT: u8 = 0x80;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EscapeWarningKind {
+    UnknownEscape(u8),
+    ByteOutOfRange,
+    MalformedByteEscape,
+    TrailingBackslash,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EscapeWarning {
+    /// Offset of the escape's backslash from the start of the token
+    pub offset: usize,
+    pub kind: EscapeWarningKind,
+}
+
+/// Unescape a string literal token, including its surrounding quotes if
+/// present. Unknown escapes are passed through verbatim and reported.
+pub fn unescape(
+    token: &[u8],
+    dialect: Dialect,
+) -> (Vec<u8>, Vec<EscapeWarning>) {
+    let (body, body_offset) = match token {
+        [b'"', body @ .., b'"'] => (body, 1),
+        _ => (token, 0),
+    };
+
+    let mut unescaped = Vec::with_capacity(body.len());
+    let mut warnings = Vec::new();
+    let mut gold = false;
+    let mut idx = 0;
+
+    let mut warn = |offset: usize, kind| {
+        warnings.push(EscapeWarning {
+            offset: offset + body_offset,
+            kind,
+        })
+    };
+
+    while idx < body.len() {
+        let start = idx;
+        let byte = body[idx];
+        idx += 1;
+
+        if byte != b'\\' {
+            unescaped.push(if gold { byte | HIGH_BIT } else { byte });
+            continue;
+        }
+
+        let Some(&escape) = body.get(idx) else {
+            warn(start, EscapeWarningKind::TrailingBackslash);
+            unescaped.push(b'\\');
+            break;
+        };
+
+        idx += 1;
+
+        let value = match (escape, dialect) {
+            (b'n', _) => b'\n',
+            (b'"', _) => b'"',
+            (b'\\', _) => b'\\',
+            (b't', Dialect::Fte) => b'\t',
+            (b'r', Dialect::Fte) => b'\r',
+            (b'\'', Dialect::Fte) => b'\'',
+            (b'[', Dialect::Fte) => 0x10,
+            (b']', Dialect::Fte) => 0x11,
+            (b'<', Dialect::Fte) => 0x1d,
+            (b'-', Dialect::Fte) => 0x1e,
+            (b'>', Dialect::Fte) => 0x1f,
+            (GOLD_TOGGLE, Dialect::Fte) => {
+                gold = !gold;
+                continue;
+            }
+            (b'{', Dialect::Fte) => {
+                let Some(len) = body[idx..].iter().position(|b| *b == b'}')
+                else {
+                    warn(start, EscapeWarningKind::MalformedByteEscape);
+                    idx = body.len();
+                    continue;
+                };
+
+                let digits = &body[idx..idx + len];
+                idx += len + 1;
+
+                match parse_byte_value(digits) {
+                    Some(value) if value <= u32::from(u8::MAX) => value as u8,
+                    Some(_) => {
+                        warn(start, EscapeWarningKind::ByteOutOfRange);
+                        continue;
+                    }
+                    None => {
+                        warn(start, EscapeWarningKind::MalformedByteEscape);
+                        continue;
+                    }
+                }
+            }
+            _ => {
+                warn(start, EscapeWarningKind::UnknownEscape(escape));
+                unescaped.push(b'\\');
+                escape
+            }
+        };
+
+        unescaped.push(if gold { value | HIGH_BIT } else { value });
+    }
+
+    (unescaped, warnings)
+}
+
+fn parse_byte_value(digits: &[u8]) -> Option<u32> {
+    let text = std::str::from_utf8(digits).ok()?;
+
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Remove `^0`-`^9` and `^xRGB` color codes; `^^` is a literal caret
+pub fn strip_color_codes(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '^' {
+            stripped.push(c);
+            continue;
+        }
+
+        match chars.peek() {
+            Some('0'..='9') => {
+                chars.next();
+            }
+            Some('^') => {
+                chars.next();
+                stripped.push('^');
+            }
+            Some('x') => {
+                let mut lookahead = chars.clone();
+                lookahead.next();
+
+                if (0..3).all(|_| {
+                    lookahead.next().is_some_and(|h| h.is_ascii_hexdigit())
+                }) {
+                    chars = lookahead;
+                } else {
+                    stripped.push(c);
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    stripped
+}
+
+/// Number of characters displayed once color codes are removed
+pub fn visible_length(text: &str) -> usize {
+    strip_color_codes(text).chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vanilla_escapes() {
+        let (text, warnings) = unescape(br#""a\n\"\\b""#, Dialect::Vanilla);
+
+        assert_eq!(text, b"a\n\"\\b");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn fte_escapes_unknown_in_vanilla() {
+        let (text, warnings) = unescape(br#""\t\[""#, Dialect::Fte);
+
+        assert_eq!(text, b"\t\x10");
+        assert!(warnings.is_empty());
+
+        let (_, warnings) = unescape(br#""\t\[""#, Dialect::Vanilla);
+
+        assert_eq!(
+            warnings,
+            vec![
+                EscapeWarning {
+                    offset: 1,
+                    kind: EscapeWarningKind::UnknownEscape(b't'),
+                },
+                EscapeWarning {
+                    offset: 3,
+                    kind: EscapeWarningKind::UnknownEscape(b'['),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gold_text_toggle() {
+        let (text, warnings) = unescape(br#""a\sbc\sd""#, Dialect::Fte);
+
+        assert_eq!(text, vec![b'a', b'b' | 0x80, b'c' | 0x80, b'd']);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn byte_escapes() {
+        let (text, warnings) = unescape(br#""\{65}\{0x42}""#, Dialect::Fte);
+
+        assert_eq!(text, b"AB");
+        assert!(warnings.is_empty());
+
+        let (text, warnings) = unescape(br#""x\{256}\{zz}\{1""#, Dialect::Fte);
+
+        assert_eq!(text, b"x");
+        assert_eq!(
+            warnings,
+            vec![
+                EscapeWarning {
+                    offset: 2,
+                    kind: EscapeWarningKind::ByteOutOfRange,
+                },
+                EscapeWarning {
+                    offset: 8,
+                    kind: EscapeWarningKind::MalformedByteEscape,
+                },
+                EscapeWarning {
+                    offset: 13,
+                    kind: EscapeWarningKind::MalformedByteEscape,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_escape_offset() {
+        let (text, warnings) = unescape(br#""ab\q""#, Dialect::Fte);
+
+        assert_eq!(text, b"ab\\q");
+        assert_eq!(
+            warnings,
+            vec![EscapeWarning {
+                offset: 3,
+                kind: EscapeWarningKind::UnknownEscape(b'q'),
+            }]
+        );
+    }
+
+    #[test]
+    fn color_codes() {
+        assert_eq!(strip_color_codes("^1red^7 white"), "red white");
+        assert_eq!(strip_color_codes("^xF80orange"), "orange");
+        assert_eq!(strip_color_codes("^xZZ^^"), "^xZZ^");
+        assert_eq!(strip_color_codes("trailing^"), "trailing^");
+        assert_eq!(visible_length("^2Hello^7!"), 6);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::dfa::Automaton;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Incremental matcher driven one symbol at a time, as used by the lexer.
+/// `None` marks the end of input.
+pub trait Recognizer<Sym> {
+    fn transition(&mut self, symbol: Option<Sym>);
+    fn is_alive(&self) -> bool;
+    /// Whether the symbols consumed since the last reset are accepted
+    fn is_accepting(&self) -> bool;
+    /// Whether the state before the last transition was accepting
+    fn is_previous_accepting(&self) -> bool;
+    fn reset(&mut self);
+}
+
+impl<Sym: Copy + Ord> Recognizer<Sym> for Automaton<Sym> {
+    fn transition(&mut self, symbol: Option<Sym>) {
+        Automaton::transition(self, symbol)
+    }
+
+    fn is_alive(&self) -> bool {
+        Automaton::is_alive(self)
+    }
+
+    fn is_accepting(&self) -> bool {
+        Automaton::is_accepting(self)
+    }
+
+    fn is_previous_accepting(&self) -> bool {
+        Automaton::is_previous_accepting(self)
+    }
+
+    fn reset(&mut self) {
+        Automaton::reset(self)
+    }
+}
+
+impl<Sym, R: Recognizer<Sym> + ?Sized> Recognizer<Sym> for Box<R> {
+    fn transition(&mut self, symbol: Option<Sym>) {
+        (**self).transition(symbol)
+    }
+
+    fn is_alive(&self) -> bool {
+        (**self).is_alive()
+    }
+
+    fn is_accepting(&self) -> bool {
+        (**self).is_accepting()
+    }
+
+    fn is_previous_accepting(&self) -> bool {
+        (**self).is_previous_accepting()
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// Recognizer backed by a transition closure over caller-defined states,
+/// for prototyping a matcher before building a table-driven automaton
+#[derive(Clone)]
+pub struct ClosureAutomaton<St, F> {
+    transition: F,
+    start: St,
+    accepting: Vec<St>,
+    current_state: Option<St>,
+    previous_accepting: bool,
+}
+
+impl<St: Copy + Eq, F> ClosureAutomaton<St, F> {
+    pub fn new(
+        start: St,
+        accepting: impl IntoIterator<Item = St>,
+        transition: F,
+    ) -> Self {
+        Self {
+            transition,
+            start,
+            accepting: accepting.into_iter().collect(),
+            current_state: Some(start),
+            previous_accepting: false,
+        }
+    }
+}
+
+impl<Sym, St, F> Recognizer<Sym> for ClosureAutomaton<St, F>
+where
+    St: Copy + Eq,
+    F: Fn(St, Option<Sym>) -> Option<St>,
+{
+    fn transition(&mut self, symbol: Option<Sym>) {
+        self.previous_accepting = self.is_accepting();
+        self.current_state = self
+            .current_state
+            .and_then(|state| (self.transition)(state, symbol));
+    }
+
+    fn is_alive(&self) -> bool {
+        self.current_state.is_some()
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.current_state
+            .is_some_and(|state| self.accepting.contains(&state))
+    }
+
+    fn is_previous_accepting(&self) -> bool {
+        self.previous_accepting
+    }
+
+    fn reset(&mut self) {
+        self.current_state = Some(self.start);
+        self.previous_accepting = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dense::DenseAutomaton;
+    use crate::dfa::{self, START};
+    use crate::lexer::{Lexer, TokenKind};
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum TestTokenKind {
+        Ab,
+        If,
+        Ident,
+        Unknown,
+    }
+
+    impl TokenKind for TestTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn closure_recognizer_in_lexer() {
+        let ab = ClosureAutomaton::new(0, [2], |state, symbol| {
+            match (state, symbol) {
+                (0, Some(b'a')) => Some(1),
+                (1, Some(b'b')) => Some(2),
+                _ => None,
+            }
+        });
+        let byte_iter = "abaab".bytes();
+
+        let tokens: Vec<_> = Lexer::new(vec![(ab, TestTokenKind::Ab)])
+            .lex(byte_iter)
+            .map(|t| (t.kind(), t.text().unwrap().to_vec()))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TestTokenKind::Ab, b"ab".to_vec()),
+                (TestTokenKind::Unknown, b"a".to_vec()),
+                (TestTokenKind::Ab, b"ab".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn mixed_recognizers() {
+        let mut ident_builder = dfa::AutomatonBuilder::new();
+        let rest = ident_builder.add_state(true);
+        ident_builder.add_transition(START, rest, b'a'..=b'z');
+        ident_builder.add_transition(rest, rest, b'a'..=b'z');
+        let ident = ident_builder.build();
+        let dense_if = DenseAutomaton::from(&dfa::keyword_automaton(*b"if"));
+        let automata: Vec<(Box<dyn Recognizer<u8>>, _)> = vec![
+            (Box::new(dense_if), TestTokenKind::If),
+            (Box::new(ident), TestTokenKind::Ident),
+        ];
+        let byte_iter = "if iffy".bytes();
+
+        let tokens: Vec<_> = Lexer::new(automata)
+            .lex(byte_iter)
+            .map(|t| (t.kind(), t.text().unwrap().to_vec()))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TestTokenKind::If, b"if".to_vec()),
+                (TestTokenKind::Unknown, b" ".to_vec()),
+                (TestTokenKind::Ident, b"iffy".to_vec()),
+            ]
+        );
+    }
+}
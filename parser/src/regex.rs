@@ -0,0 +1,352 @@
+use crate::dfa::{Automaton, START};
+use crate::interval::IntervalSet;
+use crate::nfa::{DeterminizationError, Nfa};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+/// Regex syntax error; offsets are byte positions in the pattern
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegexError {
+    UnexpectedEnd,
+    UnmatchedParen(usize),
+    UnclosedClass(usize),
+    NothingToRepeat(usize),
+    InvalidRange(usize),
+    Determinization(DeterminizationError),
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "Pattern ends unexpectedly"),
+            Self::UnmatchedParen(idx) => {
+                write!(f, "Unmatched parenthesis at byte {idx}")
+            }
+            Self::UnclosedClass(idx) => {
+                write!(f, "Character class at byte {idx} is never closed")
+            }
+            Self::NothingToRepeat(idx) => {
+                write!(f, "Repetition at byte {idx} follows nothing")
+            }
+            Self::InvalidRange(idx) => {
+                write!(f, "Range at byte {idx} ends before it starts")
+            }
+            Self::Determinization(_) => {
+                write!(f, "Pattern needs too many automaton states")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RegexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Determinization(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// State cap used by `regex_automaton` during determinization, far above
+/// what realistic token patterns need
+pub const DEFAULT_MAX_STATES: usize = 10_000;
+
+/// Build a byte automaton from a regular expression supporting literals,
+/// `.`, classes such as `[a-z_]` or `[^"]`, groups, `|`, `*`, `+` and `?`,
+/// plus the escapes `\d`, `\w`, `\s`, `\n`, `\r` and `\t`. Classes and `.`
+/// match single bytes, so non-ASCII characters only work as literals.
+/// Determinization is capped at `DEFAULT_MAX_STATES` states.
+pub fn regex_automaton(pattern: &str) -> Result<Automaton<u8>, RegexError> {
+    regex_automaton_limited(pattern, DEFAULT_MAX_STATES)
+}
+
+/// Like `regex_automaton`, failing if determinizing the pattern would need
+/// more than `max_states` states
+pub fn regex_automaton_limited(
+    pattern: &str,
+    max_states: usize,
+) -> Result<Automaton<u8>, RegexError> {
+    let mut parser = Parser {
+        pattern: pattern.as_bytes(),
+        pos: 0,
+        nfa: Nfa::new(),
+    };
+    let (start, end) = parser.alternation()?;
+
+    if parser.pos < parser.pattern.len() {
+        return Err(RegexError::UnmatchedParen(parser.pos));
+    }
+
+    parser.nfa.add_epsilon(START, start);
+    parser.nfa.set_accepting(end, true);
+    let automaton = parser
+        .nfa
+        .to_dfa_limited(max_states)
+        .map_err(RegexError::Determinization)?;
+    Ok(automaton.minimize())
+}
+
+/// Start and end states of a sub-automaton with a single exit
+type Fragment = (usize, usize);
+
+struct Parser<'a> {
+    pattern: &'a [u8],
+    pos: usize,
+    nfa: Nfa<u8>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, RegexError> {
+        let byte = self.peek().ok_or(RegexError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn alternation(&mut self) -> Result<Fragment, RegexError> {
+        let mut branches = Vec::from([self.concatenation()?]);
+
+        while self.peek() == Some(b'|') {
+            self.pos += 1;
+            branches.push(self.concatenation()?);
+        }
+
+        if let [branch] = branches[..] {
+            return Ok(branch);
+        }
+
+        let start = self.nfa.add_state(false);
+        let end = self.nfa.add_state(false);
+
+        for (branch_start, branch_end) in branches {
+            self.nfa.add_epsilon(start, branch_start);
+            self.nfa.add_epsilon(branch_end, end);
+        }
+
+        Ok((start, end))
+    }
+
+    fn concatenation(&mut self) -> Result<Fragment, RegexError> {
+        let start = self.nfa.add_state(false);
+        let mut end = start;
+
+        while !matches!(self.peek(), None | Some(b'|') | Some(b')')) {
+            let (next_start, next_end) = self.repetition()?;
+            self.nfa.add_epsilon(end, next_start);
+            end = next_end;
+        }
+
+        Ok((start, end))
+    }
+
+    fn repetition(&mut self) -> Result<Fragment, RegexError> {
+        let (mut start, mut end) = self.atom()?;
+
+        while let Some(op @ (b'*' | b'+' | b'?')) = self.peek() {
+            self.pos += 1;
+            let new_start = self.nfa.add_state(false);
+            let new_end = self.nfa.add_state(false);
+            self.nfa.add_epsilon(new_start, start);
+            self.nfa.add_epsilon(end, new_end);
+
+            if op != b'+' {
+                self.nfa.add_epsilon(new_start, new_end);
+            }
+
+            if op != b'?' {
+                self.nfa.add_epsilon(end, start);
+            }
+
+            (start, end) = (new_start, new_end);
+        }
+
+        Ok((start, end))
+    }
+
+    fn atom(&mut self) -> Result<Fragment, RegexError> {
+        let pos = self.pos;
+
+        let ranges = match self.next()? {
+            b'(' => {
+                let group = self.alternation()?;
+
+                if self.peek() != Some(b')') {
+                    return Err(RegexError::UnmatchedParen(pos));
+                }
+
+                self.pos += 1;
+                return Ok(group);
+            }
+            b')' => return Err(RegexError::UnmatchedParen(pos)),
+            b'*' | b'+' | b'?' => return Err(RegexError::NothingToRepeat(pos)),
+            b'[' => self.class(pos)?,
+            b'.' => Vec::from([0..=b'\n' - 1, b'\n' + 1..=u8::MAX]),
+            b'\\' => escape(self.next()?),
+            byte => Vec::from([byte..=byte]),
+        };
+
+        let start = self.nfa.add_state(false);
+        let end = self.nfa.add_state(false);
+
+        for range in ranges {
+            self.nfa.add_transition(start, end, range);
+        }
+
+        Ok((start, end))
+    }
+
+    fn class(
+        &mut self,
+        open: usize,
+    ) -> Result<Vec<RangeInclusive<u8>>, RegexError> {
+        let negated = self.peek() == Some(b'^');
+
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut set = IntervalSet::new();
+        let mut first = true;
+
+        loop {
+            let pos = self.pos;
+            let byte =
+                self.next().map_err(|_| RegexError::UnclosedClass(open))?;
+
+            let ranges = match byte {
+                b']' if !first => break,
+                b'\\' => escape(
+                    self.next().map_err(|_| RegexError::UnclosedClass(open))?,
+                ),
+                byte => Vec::from([byte..=byte]),
+            };
+            first = false;
+
+            let is_range = self.peek() == Some(b'-')
+                && !matches!(self.pattern.get(self.pos + 1), None | Some(b']'));
+
+            match (&ranges[..], is_range) {
+                ([single], true) if single.start() == single.end() => {
+                    self.pos += 1;
+                    let end = match self.next()? {
+                        b'\\' => *escape(self.next()?)[0].end(),
+                        byte => byte,
+                    };
+
+                    if end < *single.start() {
+                        return Err(RegexError::InvalidRange(pos));
+                    }
+
+                    set.insert(*single.start()..=end);
+                }
+                _ => ranges.into_iter().for_each(|range| set.insert(range)),
+            }
+        }
+
+        if negated {
+            set = set.complement(0..=u8::MAX);
+        }
+
+        Ok(set.ranges().to_vec())
+    }
+}
+
+fn escape(byte: u8) -> Vec<RangeInclusive<u8>> {
+    match byte {
+        b'd' => Vec::from([b'0'..=b'9']),
+        b'w' => Vec::from([b'0'..=b'9', b'A'..=b'Z', b'_'..=b'_', b'a'..=b'z']),
+        b's' => Vec::from([b'\t'..=b'\r', b' '..=b' ']),
+        b'n' => Vec::from([b'\n'..=b'\n']),
+        b'r' => Vec::from([b'\r'..=b'\r']),
+        b't' => Vec::from([b'\t'..=b'\t']),
+        byte => Vec::from([byte..=byte]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_matches() {
+        let ident = regex_automaton("[a-zA-Z_][a-zA-Z0-9_]*").unwrap();
+
+        assert!(ident.accepts(*b"_hello123"));
+        assert!(!ident.accepts(*b"1abc"));
+        assert!(!ident.accepts(*b""));
+
+        let float = regex_automaton(r"-?(\d+\.\d*|\.\d+)").unwrap();
+
+        assert!(float.accepts(*b"-1."));
+        assert!(float.accepts(*b".5"));
+        assert!(!float.accepts(*b"."));
+
+        let string = regex_automaton(r#""([^"\\]|\\.)*""#).unwrap();
+
+        assert!(string.accepts(*br#""a\"b""#));
+        assert!(!string.accepts(*br#""a"b""#));
+
+        let keywords = regex_automaton("if|while|").unwrap();
+
+        assert!(keywords.accepts(*b"while"));
+        assert!(keywords.accepts(*b""));
+        assert!(!keywords.accepts(*b"whi"));
+    }
+
+    #[test]
+    fn regex_errors() {
+        assert_eq!(
+            regex_automaton("(ab").err(),
+            Some(RegexError::UnmatchedParen(0))
+        );
+        assert_eq!(
+            regex_automaton("ab)").err(),
+            Some(RegexError::UnmatchedParen(2))
+        );
+        assert_eq!(
+            regex_automaton("a[bc").err(),
+            Some(RegexError::UnclosedClass(1))
+        );
+        assert_eq!(
+            regex_automaton("a|*").err(),
+            Some(RegexError::NothingToRepeat(2))
+        );
+        assert_eq!(
+            regex_automaton("[z-a]").err(),
+            Some(RegexError::InvalidRange(1))
+        );
+        assert_eq!(
+            regex_automaton("a\\").err(),
+            Some(RegexError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn regex_state_limit() {
+        // (a|b)*a(a|b){n}: the DFA must remember the last n + 1 symbols
+        let blowup = |n| format!("(a|b)*a{}", "(a|b)".repeat(n));
+
+        assert_eq!(
+            regex_automaton(&blowup(16)).err(),
+            Some(RegexError::Determinization(
+                DeterminizationError::TooManyStates
+            ))
+        );
+        assert_eq!(
+            regex_automaton_limited(&blowup(8), 100).err(),
+            Some(RegexError::Determinization(
+                DeterminizationError::TooManyStates
+            ))
+        );
+
+        let automaton = regex_automaton_limited(&blowup(3), 100).unwrap();
+
+        assert!(automaton.accepts(*b"babbb"));
+        assert!(!automaton.accepts(*b"bbbbb"));
+    }
+}
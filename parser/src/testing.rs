@@ -0,0 +1,264 @@
+use crate::dfa::{Automaton, AutomatonBuilder, START};
+use crate::lexer::{Lexer, TokenKind};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
+
+/// Cloneable description of an `Automaton<u8>`, as generated by
+/// `automaton_spec`
+#[derive(Clone, Debug)]
+pub struct AutomatonSpec {
+    pub accepting: Vec<bool>,
+    pub transitions: Vec<(usize, usize, RangeInclusive<u8>)>,
+}
+
+impl AutomatonSpec {
+    pub fn keyword(keyword: &[u8]) -> Self {
+        Self {
+            accepting: (0..=keyword.len())
+                .map(|i| i == keyword.len())
+                .collect(),
+            transitions: keyword
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| (i, i + 1, *byte..=*byte))
+                .collect(),
+        }
+    }
+
+    pub fn build(&self) -> Automaton<u8> {
+        let mut builder = AutomatonBuilder::new();
+
+        for accepting in &self.accepting[1..] {
+            builder.add_state(*accepting);
+        }
+
+        for (from, to, symbols) in &self.transitions {
+            builder.add_transition(*from, *to, symbols.clone());
+        }
+
+        builder.build()
+    }
+}
+
+/// Automata with up to `max_states` states over `alphabet`. START is never
+/// accepting, since the lexer cannot emit empty tokens.
+pub fn automaton_spec(
+    max_states: usize,
+    alphabet: RangeInclusive<u8>,
+) -> impl Strategy<Value = AutomatonSpec> {
+    (1..=max_states.max(1)).prop_flat_map(move |state_count| {
+        let symbol = alphabet.clone();
+        let transition =
+            (0..state_count, 0..state_count, symbol.clone(), symbol)
+                .prop_map(|(from, to, a, b)| (from, to, a.min(b)..=a.max(b)));
+
+        (
+            proptest::collection::vec(any::<bool>(), state_count),
+            proptest::collection::vec(transition, 0..state_count * 3),
+        )
+            .prop_map(|(mut accepting, transitions)| {
+                accepting[START] = false;
+                AutomatonSpec {
+                    accepting,
+                    transitions,
+                }
+            })
+    })
+}
+
+/// One generated automaton per kind, in the order given
+pub fn rule_set<K: Clone + Debug + 'static>(
+    kinds: Vec<K>,
+    max_states: usize,
+    alphabet: RangeInclusive<u8>,
+) -> impl Strategy<Value = Vec<(AutomatonSpec, K)>> {
+    let specs = kinds.len();
+
+    proptest::collection::vec(automaton_spec(max_states, alphabet), specs)
+        .prop_map(move |specs| specs.into_iter().zip(kinds.clone()).collect())
+}
+
+/// Inputs built from keywords, their proper prefixes, keywords followed by
+/// a `filler` byte, and lone filler bytes, to exercise maximal munch
+pub fn boundary_input(
+    keywords: Vec<Vec<u8>>,
+    filler: RangeInclusive<u8>,
+) -> impl Strategy<Value = Vec<u8>> {
+    let keyword = proptest::sample::select(keywords);
+    let piece = prop_oneof![
+        keyword.clone(),
+        keyword.clone().prop_flat_map(|k| {
+            let len = k.len();
+            (Just(k), 0..len.max(1)).prop_map(|(k, n)| k[..n].to_vec())
+        }),
+        (keyword, filler.clone()).prop_map(|(mut k, b)| {
+            k.push(b);
+            k
+        }),
+        filler.prop_map(|b| vec![b]),
+    ];
+
+    proptest::collection::vec(piece, 0..8).prop_map(|pieces| pieces.concat())
+}
+
+/// Reference semantics of `Lexer::lex`: extend while any automaton is alive,
+/// then take the first automaton accepting the whole run, else `unknown`
+pub fn reference_lex<K: TokenKind>(
+    automata: &[(Automaton<u8>, K)],
+    input: &[u8],
+) -> Vec<(K, Vec<u8>)> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+
+    while start < input.len() {
+        let rest = &input[start..];
+        let len = (1..=rest.len())
+            .rev()
+            .find(|len| {
+                automata
+                    .iter()
+                    .any(|(a, _)| a.is_prefix(rest[..*len].iter().copied()))
+            })
+            .unwrap_or(1);
+        let text = &rest[..len];
+        let kind = automata
+            .iter()
+            .find(|(a, _)| a.accepts(text.iter().copied()))
+            .map_or(K::unknown(), |(_, kind)| *kind);
+
+        tokens.push((kind, text.to_vec()));
+        start += len;
+    }
+
+    tokens
+}
+
+/// The concatenated token texts reproduce `input`. Every kind, including
+/// `K::unknown()`, must keep its text, and trivia must be emitted.
+pub fn prop_tokens_tile_input<K: TokenKind>(
+    lexer: Lexer<u8, K>,
+    input: &[u8],
+) -> Result<(), TestCaseError> {
+    let mut tiled = Vec::new();
+
+    for token in lexer.lex(input.iter().copied()) {
+        match token.text() {
+            Some(text) => tiled.extend_from_slice(text),
+            None => return Err(TestCaseError::fail("token kind without text")),
+        }
+    }
+
+    prop_assert_eq!(tiled, input);
+    Ok(())
+}
+
+/// The kinds (and texts, for kinds with text) produced by `lexer` match
+/// `reference`
+pub fn prop_lex_matches_reference<K: TokenKind + Debug>(
+    lexer: Lexer<u8, K>,
+    input: &[u8],
+    reference: &[(K, Vec<u8>)],
+) -> Result<(), TestCaseError> {
+    let tokens: Vec<_> = lexer.lex(input.iter().copied()).collect();
+
+    prop_assert_eq!(tokens.len(), reference.len());
+
+    for (token, (kind, text)) in tokens.iter().zip(reference) {
+        prop_assert_eq!(token.kind(), *kind);
+
+        if kind.has_text() {
+            prop_assert_eq!(token.text(), Some(&text[..]));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    enum TestTokenKind {
+        If,
+        Ifdef,
+        Ident,
+        Other,
+        Unknown,
+    }
+
+    impl TokenKind for TestTokenKind {
+        fn unknown() -> Self {
+            Self::Unknown
+        }
+
+        fn has_text(&self) -> bool {
+            true
+        }
+    }
+
+    fn ident_spec() -> AutomatonSpec {
+        AutomatonSpec {
+            accepting: vec![false, true],
+            transitions: vec![(0, 1, b'a'..=b'z'), (1, 1, b'a'..=b'z')],
+        }
+    }
+
+    fn keyword_rules() -> Vec<(AutomatonSpec, TestTokenKind)> {
+        vec![
+            (AutomatonSpec::keyword(b"if"), TestTokenKind::If),
+            (AutomatonSpec::keyword(b"ifdef"), TestTokenKind::Ifdef),
+            (ident_spec(), TestTokenKind::Ident),
+        ]
+    }
+
+    fn build(
+        rules: &[(AutomatonSpec, TestTokenKind)],
+    ) -> Vec<(Automaton<u8>, TestTokenKind)> {
+        rules
+            .iter()
+            .map(|(spec, kind)| (spec.build(), *kind))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn keyword_lexer_tiles_input(
+            input in boundary_input(
+                vec![b"if".to_vec(), b"ifdef".to_vec()],
+                b' '..=b'z',
+            )
+        ) {
+            prop_tokens_tile_input(Lexer::new(build(&keyword_rules())), &input)?;
+        }
+
+        #[test]
+        fn keyword_lexer_matches_reference(
+            input in boundary_input(
+                vec![b"if".to_vec(), b"ifdef".to_vec()],
+                b' '..=b'z',
+            )
+        ) {
+            let rules = build(&keyword_rules());
+            let reference = reference_lex(&rules, &input);
+            prop_assert_eq!(reference.is_empty(), input.is_empty());
+            prop_lex_matches_reference(Lexer::new(rules), &input, &reference)?;
+        }
+
+        #[test]
+        fn random_rules_match_reference(
+            rules in rule_set(
+                vec![TestTokenKind::Ident, TestTokenKind::Other],
+                4,
+                b'a'..=b'd',
+            ),
+            input in proptest::collection::vec(b'a'..=b'e', 0..16),
+        ) {
+            let reference = reference_lex(&build(&rules), &input);
+            prop_tokens_tile_input(Lexer::new(build(&rules)), &input)?;
+            prop_lex_matches_reference(Lexer::new(build(&rules)), &input, &reference)?;
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Lexing into a bump arena. Lives in its own test binary so the counting
+//! global allocator sees no other tests' frees.
+
+use bumpalo::Bump;
+use qct_parser::dfa::{self, AutomatonBuilder, START};
+use qct_parser::lexer::{Lexer, TokenKind};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static FREES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        FREES.fetch_add(1, Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ArenaTokenKind {
+    Let,
+    Ident,
+    Number,
+    Unknown,
+}
+
+impl TokenKind for ArenaTokenKind {
+    fn unknown() -> Self {
+        Self::Unknown
+    }
+
+    fn has_text(&self) -> bool {
+        matches!(self, Self::Ident | Self::Number)
+    }
+}
+
+fn lexer() -> Lexer<u8, ArenaTokenKind> {
+    let mut ident = AutomatonBuilder::<u8>::new();
+    let rest = ident.add_state(true);
+    ident.add_transition(START, rest, b'a'..=b'z');
+    ident.add_transition(rest, rest, b'a'..=b'z');
+    ident.add_transition(rest, rest, b'0'..=b'9');
+
+    let mut number = AutomatonBuilder::<u8>::new();
+    let digits = number.add_state(true);
+    number.add_transition(START, digits, b'0'..=b'9');
+    number.add_transition(digits, digits, b'0'..=b'9');
+
+    Lexer::new(vec![
+        (dfa::keyword_automaton(*b"let"), ArenaTokenKind::Let),
+        (ident.build(), ArenaTokenKind::Ident),
+        (number.build(), ArenaTokenKind::Number),
+    ])
+}
+
+#[test]
+fn lex_into_arena() {
+    let source = "let x1 = 42; let longer_name = 7;\n".repeat(1000);
+    let bump = Bump::with_capacity(64 * 1024);
+    let mut tokens = Vec::with_capacity(source.len());
+    let mut token_iter = lexer().lex_in(source.bytes(), &bump);
+
+    let frees = FREES.load(Ordering::SeqCst);
+    tokens.extend(token_iter.by_ref());
+    assert_eq!(FREES.load(Ordering::SeqCst), frees);
+    drop(token_iter);
+
+    let texts: Vec<_> = tokens
+        .iter()
+        .filter_map(|token| token.text())
+        .take(6)
+        .collect();
+
+    assert_eq!(texts, [&b"x1"[..], b"42", b"longer", b"name", b"7", b"x1"]);
+    assert_eq!(
+        tokens
+            .iter()
+            .filter(|t| t.kind() == ArenaTokenKind::Let)
+            .count(),
+        2000
+    );
+}